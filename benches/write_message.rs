@@ -0,0 +1,135 @@
+//! Number of writes, and time, to send DoIP messages on an unbuffered socket
+//!
+//! Each write on an unbuffered `TcpStream` is a syscall, and with `TCP_NODELAY`
+//! a TCP segment. The "fields" columns encode the header and the payload
+//! directly into the writer, field by field, as `write_message()` used to do,
+//! while the "gathered" columns use `write_message()`.
+//!
+//! Run with `cargo bench --bench write_message`.
+use std::io::{self, IoSlice, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use doip_rw::message::*;
+use doip_rw::{write_message, DoIpHeader, Payload};
+
+const MESSAGES: u32 = 20_000;
+
+/// Writer counting the calls to `write()` and `write_vectored()`.
+struct CountingWriter<W> {
+    inner: W,
+    writes: usize,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.writes += 1;
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes the header and then the payload, each field with its own write.
+fn write_field_by_field<W: Write, P: Payload>(payload: &P, writer: &mut W) {
+    let header = DoIpHeader::new(P::payload_type(), payload.length() as u32);
+    header.write(writer).unwrap();
+    payload.write(writer).unwrap();
+}
+
+fn write_gathered<W: Write, P: Payload>(payload: &P, writer: &mut W) {
+    write_message(payload, writer).unwrap();
+}
+
+fn count_writes<P: Payload>(payload: &P, write: fn(&P, &mut CountingWriter<io::Sink>)) -> usize {
+    let mut writer = CountingWriter {
+        inner: io::sink(),
+        writes: 0,
+    };
+    write(payload, &mut writer);
+    writer.writes
+}
+
+/// Sends `MESSAGES` times the payload on a loopback TCP connection.
+fn time_loopback<P: Payload>(payload: &P, write: fn(&P, &mut TcpStream)) -> Duration {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut tcp = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    tcp.set_nodelay(true).unwrap();
+    let (mut peer, _) = listener.accept().unwrap();
+    let total = MESSAGES as u64 * (doip_rw::length_message(payload) as u64);
+    let reader = thread::spawn(move || {
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut received = 0;
+        while received < total {
+            match peer.read(&mut buf).unwrap() {
+                0 => break,
+                n => received += n as u64,
+            }
+        }
+    });
+
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        write(payload, &mut tcp);
+    }
+    reader.join().unwrap();
+    start.elapsed()
+}
+
+fn bench<P: Payload>(name: &str, payload: &P) {
+    println!(
+        "{:<32} {:>8} {:>8} {:>12?} {:>12?}",
+        name,
+        count_writes(payload, write_field_by_field),
+        count_writes(payload, write_gathered),
+        time_loopback(payload, write_field_by_field) / MESSAGES,
+        time_loopback(payload, write_gathered) / MESSAGES,
+    );
+}
+
+fn main() {
+    println!(
+        "{:<32} {:>8} {:>8} {:>12} {:>12}",
+        "", "writes", "writes", "time/msg", "time/msg"
+    );
+    println!(
+        "{:<32} {:>8} {:>8} {:>12} {:>12}",
+        "message", "fields", "gathered", "fields", "gathered"
+    );
+    bench(
+        "AliveCheckResponse",
+        &AliveCheckResponse {
+            source_address: 0x0e00,
+        },
+    );
+    bench(
+        "RoutingActivationRequest",
+        &RoutingActivationRequest::builder(0x0e00).build(),
+    );
+    bench(
+        "VehicleIdentificationResponse",
+        &VehicleIdentificationResponse::builder(
+            "VF1YYYYYZTT000001".parse().unwrap(),
+            0x0077,
+            Eid::from_mac([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]),
+        )
+        .vin_gid_sync_status(VinGidSyncStatus::Synchronized)
+        .build(),
+    );
+    bench(
+        "DiagnosticMessage (3 bytes)",
+        &DiagnosticMessage::new(0x0e00, 0x0077, vec![0x22, 0xf1, 0x90]),
+    );
+    bench(
+        "DiagnosticMessage (4 KiB)",
+        &DiagnosticMessage::new(0x0e00, 0x0077, vec![0x36; 4096]),
+    );
+}
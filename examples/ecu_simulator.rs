@@ -0,0 +1,73 @@
+use std::thread;
+
+use doip_rw::message::*;
+use doip_rw::{AnnouncerConfig, VehicleAnnouncer};
+use doip_rw::{DiagnosticHandler, DiagnosticResponse, DoIpServer, LogicalAddress, ServerConfig};
+
+const ENTITY_LOGICAL_ADDRESS: LogicalAddress = 0x0077;
+
+/// A simulated ECU, answering a few UDS services.
+#[derive(Clone)]
+struct Ecu {
+    session: u8,
+}
+
+impl DiagnosticHandler for Ecu {
+    fn handle(
+        &mut self,
+        source: LogicalAddress,
+        target: LogicalAddress,
+        uds: &[u8],
+    ) -> DiagnosticResponse {
+        println!("{:04x} -> {:04x} : {:02x?}", source, target, uds);
+        match uds {
+            // DiagnosticSessionControl
+            [0x10, session] => {
+                self.session = *session;
+                DiagnosticResponse::Response(vec![0x50, *session, 0x00, 0x32, 0x01, 0xf4])
+            }
+            // TesterPresent, with suppressPosRspMsgIndicationBit
+            [0x3e, 0x80] => DiagnosticResponse::NoResponse,
+            // TesterPresent
+            [0x3e, 0x00] => DiagnosticResponse::Response(vec![0x7e, 0x00]),
+            // ReadDataByIdentifier
+            [0x22, did_hi, did_lo] => {
+                DiagnosticResponse::Response(vec![0x62, *did_hi, *did_lo, self.session])
+            }
+            [sid, ..] => DiagnosticResponse::Response(vec![0x7f, *sid, 0x11]),
+            [] => DiagnosticResponse::NoResponse,
+        }
+    }
+
+    fn accept(
+        &mut self,
+        _source: LogicalAddress,
+        _target: LogicalAddress,
+        uds: &[u8],
+    ) -> Result<(), DiagnosticMessageNegativeAckCode> {
+        match uds {
+            [] => Err(DiagnosticMessageNegativeAckCode::TransportProtocolError),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn main() -> Result<(), doip_rw::DoIpError> {
+    let announcement = VehicleIdentificationResponse::builder(
+        "VF1YYYYYZTT000001".parse()?,
+        ENTITY_LOGICAL_ADDRESS,
+        Eid::from_mac([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]),
+    )
+    .vin_gid_sync_status(VinGidSyncStatus::Synchronized)
+    .build();
+    let announcer = VehicleAnnouncer::bind("127.0.0.1:13400", AnnouncerConfig::new(announcement))?;
+    thread::spawn(move || {
+        announcer.announce("127.0.0.1:13401")?;
+        announcer.run()
+    });
+
+    let config = ServerConfig::new(ENTITY_LOGICAL_ADDRESS);
+    let server = DoIpServer::bind("127.0.0.1:13400", config, Ecu { session: 0x01 })?;
+    println!("ECU simulator listening on {}", server.local_addr()?);
+    server.run()
+}
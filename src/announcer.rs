@@ -0,0 +1,378 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::message::*;
+use crate::{encode_message_into, read_any_payload, read_header};
+use crate::{DoIpError, DoIpMessage, HeaderValidator, Payload};
+
+/// Maximum size of a received UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 1500;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Configuration of a [`VehicleAnnouncer`].
+///
+/// The default timings are the ones of ISO 13400-2.
+pub struct AnnouncerConfig {
+    /// Vehicle announcement, also sent as identification response.
+    pub announcement: VehicleIdentificationResponse,
+    /// Response to entity status requests.
+    pub entity_status: EntityStatusResponse,
+    /// Response to power mode requests.
+    pub power_mode: PowerModeResponse,
+    /// Number of vehicle announcements sent at startup, ie.
+    /// A_DoIP_Announce_Num (3).
+    pub announce_num: u32,
+    /// Time between the vehicle announcements, ie. A_DoIP_Announce_Interval
+    /// (500ms).
+    pub announce_interval: Duration,
+    /// Maximum random delay before a vehicle announcement or identification
+    /// response, ie. A_DoIP_Announce_Wait (500ms).
+    pub announce_wait: Duration,
+}
+
+impl AnnouncerConfig {
+    /// Creates a configuration with default timings, announcing `announcement`.
+    ///
+    /// The entity status and power mode responses describe a ready node,
+    /// accepting one connection.
+    pub fn new(announcement: VehicleIdentificationResponse) -> Self {
+        Self {
+            announcement,
+            entity_status: EntityStatusResponse::builder(NodeType::Node, 1)
+                .max_data_size(HeaderValidator::default().max_payload_length)
+                .build(),
+            power_mode: PowerModeResponse {
+                power_mode: PowerMode::Ready,
+            },
+            announce_num: 3,
+            announce_interval: Duration::from_millis(500),
+            announce_wait: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Vehicle announcement and identification UDP service of a DoIP entity
+///
+/// The announcer sends the vehicle announcements when the entity starts, and
+/// answers the UDP requests of the external testers :
+/// - vehicle identification requests, if the requested EID or VIN matches,
+///   after a random delay of at most [`AnnouncerConfig::announce_wait`],
+///   during which the other requests are still answered,
+/// - entity status requests,
+/// - power mode requests.
+///
+/// Incorrect DoIP headers are answered with the appropriate
+/// [`GenericDoIpHeaderNegativeAcknowledge`].
+///
+/// Example:
+/// ```no_run
+/// use doip_rw::message::*;
+/// use doip_rw::{AnnouncerConfig, VehicleAnnouncer};
+///
+/// let announcement = VehicleIdentificationResponse::builder(
+///     "VF1YYYYYZTT000001".parse()?,
+///     0x0077,
+///     Eid::from_mac([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]),
+/// )
+/// .vin_gid_sync_status(VinGidSyncStatus::Synchronized)
+/// .build();
+/// let announcer = VehicleAnnouncer::bind("0.0.0.0:13400", AnnouncerConfig::new(announcement))?;
+/// announcer.announce("255.255.255.255:13401")?;
+/// announcer.run()?;
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub struct VehicleAnnouncer {
+    udp: UdpSocket,
+    config: AnnouncerConfig,
+    validator: HeaderValidator,
+    /// State of the random delays generator.
+    random: AtomicU64,
+}
+
+impl VehicleAnnouncer {
+    /// Creates an announcer listening on `addr`, usually on
+    /// [`crate::UDP_DISCOVERY_PORT`].
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: AnnouncerConfig) -> Result<Self, DoIpError> {
+        let udp = UdpSocket::bind(addr)?;
+        udp.set_broadcast(true)?;
+        Ok(Self {
+            udp,
+            config,
+            validator: HeaderValidator::new(MAX_DATAGRAM_SIZE as u32),
+            random: AtomicU64::new(RandomState::new().build_hasher().finish()),
+        })
+    }
+
+    /// Get the local address the announcer is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, DoIpError> {
+        Ok(self.udp.local_addr()?)
+    }
+
+    /// Sends the startup vehicle announcements to `addr`, usually a broadcast
+    /// address on [`crate::UDP_TEST_EQUIPMENT_PORT`].
+    ///
+    /// This function blocks until all the announcements are sent.
+    pub fn announce<A: ToSocketAddrs>(&self, addr: A) -> Result<(), DoIpError> {
+        let addr: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        thread::sleep(self.random_delay());
+        for i in 0..self.config.announce_num {
+            if i > 0 {
+                thread::sleep(self.config.announce_interval);
+            }
+            self.send(&self.config.announcement, &addr[..])?;
+        }
+        Ok(())
+    }
+
+    /// Answers the testers requests.
+    ///
+    /// A failure to answer a tester is ignored, and this function only returns
+    /// if the reception fails.
+    pub fn run(&self) -> Result<(), DoIpError> {
+        let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+        // Identification responses waiting for their random delay, with their
+        // deadline.
+        let mut delayed: Vec<(Instant, SocketAddr)> = vec![];
+        loop {
+            let now = Instant::now();
+            delayed.retain(|(deadline, tester)| {
+                if *deadline > now {
+                    return true;
+                }
+                let _ = self.send(&self.config.announcement, tester);
+                false
+            });
+            let next = delayed.iter().map(|(deadline, _)| *deadline).min();
+            self.udp
+                .set_read_timeout(next.map(|deadline| deadline - now))?;
+            let (len, tester) = match self.udp.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                // An ICMP error of a previous answer
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let _ = self.handle(&buf[..len], tester, &mut delayed);
+        }
+    }
+
+    fn handle(
+        &self,
+        datagram: &[u8],
+        tester: SocketAddr,
+        delayed: &mut Vec<(Instant, SocketAddr)>,
+    ) -> Result<(), DoIpError> {
+        let mut reader = datagram;
+        let Ok(header) = read_header(&mut reader) else {
+            return Ok(());
+        };
+        if let Err(e) = self.validator.validate(&header) {
+            if let Some(nack) = e.header_nack() {
+                self.send(&nack, tester)?;
+            }
+            return Ok(());
+        }
+
+        let announcement = &self.config.announcement;
+        match read_any_payload(&mut reader, &header) {
+            Ok(DoIpMessage::VehicleIdentificationRequest(_)) => self.identify(tester, delayed),
+            Ok(DoIpMessage::VehicleIdentificationRequestWithEid(request))
+                if request.eid == announcement.eid =>
+            {
+                self.identify(tester, delayed)
+            }
+            Ok(DoIpMessage::VehicleIdentificationRequestWithVin(request))
+                if request.vin == announcement.vin =>
+            {
+                self.identify(tester, delayed)
+            }
+            Ok(DoIpMessage::EntityStatusRequest(_)) => {
+                self.send(&self.config.entity_status, tester)
+            }
+            Ok(DoIpMessage::PowerModeRequest(_)) => self.send(&self.config.power_mode, tester),
+            _ => Ok(()),
+        }
+    }
+
+    /// Schedules the identification response to `tester`.
+    fn identify(
+        &self,
+        tester: SocketAddr,
+        delayed: &mut Vec<(Instant, SocketAddr)>,
+    ) -> Result<(), DoIpError> {
+        delayed.push((Instant::now() + self.random_delay(), tester));
+        Ok(())
+    }
+
+    /// Returns a random delay of at most [`AnnouncerConfig::announce_wait`],
+    /// with the SplitMix64 generator.
+    fn random_delay(&self) -> Duration {
+        let max = self.config.announce_wait.as_millis() as u64;
+        if max == 0 {
+            return Duration::ZERO;
+        }
+        const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut z = self
+            .random
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Duration::from_millis(z % (max + 1))
+    }
+
+    fn send<P: Payload, A: ToSocketAddrs>(&self, payload: &P, addr: A) -> Result<(), DoIpError> {
+        let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+        let len = encode_message_into(payload, &mut buf)?;
+        self.udp.send_to(&buf[..len], addr)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        collect_announcements, discover_vehicles_with, read_any_message, read_message,
+        write_message, DiscoveryRequest,
+    };
+
+    fn config() -> AnnouncerConfig {
+        let mut config = AnnouncerConfig::new(VehicleIdentificationResponse {
+            vin: Vin(*b"VF1YYYYYZTT000001"),
+            logical_address: 0x0077,
+            eid: Eid([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]),
+            gid: None,
+            further_action: FurtherActionRequired::NoFurtherActionRequired,
+            vin_gid_sync_status: Some(VinGidSyncStatus::Synchronized),
+        });
+        config.announce_interval = Duration::from_millis(10);
+        config.announce_wait = Duration::from_millis(10);
+        config
+    }
+
+    fn announcer() -> SocketAddr {
+        let announcer = VehicleAnnouncer::bind("127.0.0.1:0", config()).unwrap();
+        let addr = announcer.local_addr().unwrap();
+        thread::spawn(move || announcer.run());
+        addr
+    }
+
+    #[test]
+    fn announce() {
+        let tester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let announcer = VehicleAnnouncer::bind("127.0.0.1:0", config()).unwrap();
+        announcer.announce(tester.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+        for _ in 0..3 {
+            let len = tester.recv(&mut buf).unwrap();
+            let announcement: VehicleIdentificationResponse =
+                read_message(&mut &buf[..len]).unwrap();
+            assert_eq!(announcement, config().announcement);
+        }
+        let vehicles = collect_announcements(&tester, Duration::from_millis(50)).unwrap();
+        assert!(vehicles.is_empty());
+    }
+
+    #[test]
+    fn identification() {
+        let timeout = Duration::from_millis(100);
+        let entity = announcer();
+        let expected = vec![(entity, config().announcement)];
+        for (request, found) in [
+            (DiscoveryRequest::All, true),
+            (DiscoveryRequest::Eid(config().announcement.eid), true),
+            (DiscoveryRequest::Eid(Eid([0; 6])), false),
+            (DiscoveryRequest::Vin(config().announcement.vin), true),
+            (DiscoveryRequest::Vin(Vin([0x30; 17])), false),
+        ] {
+            let vehicles = discover_vehicles_with("127.0.0.1:0", entity, request, timeout).unwrap();
+            assert_eq!(vehicles == expected, found, "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn identification_delay_does_not_block() {
+        let mut config = config();
+        config.announce_wait = Duration::from_secs(1);
+        let announcer = VehicleAnnouncer::bind("127.0.0.1:0", config).unwrap();
+        let entity = announcer.local_addr().unwrap();
+        thread::spawn(move || announcer.run());
+
+        let tester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tester
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut request = vec![];
+        write_message(&VehicleIdentificationRequest {}, &mut request).unwrap();
+        tester.send_to(&request, entity).unwrap();
+        let mut request = vec![];
+        write_message(&EntityStatusRequest {}, &mut request).unwrap();
+        let sent = Instant::now();
+        tester.send_to(&request, entity).unwrap();
+
+        let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+        let mut received = vec![];
+        for _ in 0..2 {
+            let len = tester.recv(&mut buf).unwrap();
+            let message = read_any_message(&mut &buf[..len]).unwrap();
+            received.push((message, sent.elapsed()));
+        }
+        let status_delay = received
+            .iter()
+            .find(|(message, _)| matches!(message, DoIpMessage::EntityStatusResponse(_)))
+            .map(|(_, delay)| *delay)
+            .unwrap();
+        assert!(status_delay < Duration::from_millis(200));
+        assert!(received
+            .iter()
+            .any(|(message, _)| matches!(message, DoIpMessage::VehicleIdentificationResponse(_))));
+    }
+
+    #[test]
+    fn random_delays() {
+        let announcer = VehicleAnnouncer::bind("127.0.0.1:0", config()).unwrap();
+        let delays: Vec<Duration> = (0..64).map(|_| announcer.random_delay()).collect();
+        assert!(delays.iter().all(|d| *d <= config().announce_wait));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+
+    #[test]
+    fn status_requests() {
+        let entity = announcer();
+        let tester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tester
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let recv = |request: &[u8]| {
+            let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+            tester.send_to(request, entity).unwrap();
+            let len = tester.recv(&mut buf).unwrap();
+            buf[..len].to_vec()
+        };
+
+        let mut request = vec![];
+        write_message(&EntityStatusRequest {}, &mut request).unwrap();
+        let response: EntityStatusResponse = read_message(&mut &recv(&request)[..]).unwrap();
+        assert_eq!(response, config().entity_status);
+
+        let mut request = vec![];
+        write_message(&PowerModeRequest {}, &mut request).unwrap();
+        let response: PowerModeResponse = read_message(&mut &recv(&request)[..]).unwrap();
+        assert_eq!(response, config().power_mode);
+
+        let request = [0x02, 0xfd, 0x12, 0x34, 0x00, 0x00, 0x00, 0x00];
+        let nack: GenericDoIpHeaderNegativeAcknowledge =
+            read_message(&mut &recv(&request)[..]).unwrap();
+        assert_eq!(nack.nack_code, NegativeAckCode::UnknownPayloadType);
+    }
+}
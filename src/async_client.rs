@@ -0,0 +1,720 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+
+use bytes::BytesMut;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::codec::Decoder;
+
+use crate::client::is_response_pending;
+use crate::message::*;
+use crate::{
+    write_message_versioned, ClientTimeouts, DoIpCodec, DoIpError, DoIpMessage, LogicalAddress,
+};
+use crate::{Payload, DOIP_HEADER_LENGTH};
+
+/// Write half of the connection, with the protocol version of the sent
+/// messages.
+struct Writer {
+    stream: OwnedWriteHalf,
+    version: ProtocolVersion,
+}
+
+/// Outstanding requests key, ie. (target_address, source_address) of the
+/// request.
+type RequestKey = (LogicalAddress, LogicalAddress);
+
+enum Event {
+    Ack,
+    Nack(DiagnosticMessageNegativeAckCode),
+    HeaderNack(NegativeAckCode),
+    Response(Vec<u8>),
+    Closed,
+}
+
+struct PendingRequest {
+    id: u64,
+    acked: bool,
+    /// UDS request, to match the response pending responses against.
+    request: Vec<u8>,
+    events: UnboundedSender<Event>,
+}
+
+#[derive(Default)]
+struct Pending {
+    next_id: u64,
+    closed: bool,
+    requests: HashMap<RequestKey, VecDeque<PendingRequest>>,
+    /// Pending routing activation, receiving the responses or the header
+    /// negative acknowledges.
+    routing: Option<UnboundedSender<Result<RoutingActivationResponse, NegativeAckCode>>>,
+}
+
+impl Pending {
+    fn push(
+        &mut self,
+        key: RequestKey,
+        request: &[u8],
+    ) -> Result<(u64, UnboundedReceiver<Event>), DoIpError> {
+        if self.closed {
+            return Err(closed_error());
+        }
+        let (events, receiver) = unbounded_channel();
+        let id = self.next_id;
+        self.next_id += 1;
+        self.requests
+            .entry(key)
+            .or_default()
+            .push_back(PendingRequest {
+                id,
+                acked: false,
+                request: request.to_vec(),
+                events,
+            });
+        Ok((id, receiver))
+    }
+
+    fn remove(&mut self, key: RequestKey, id: u64) {
+        if let Some(queue) = self.requests.get_mut(&key) {
+            queue.retain(|r| r.id != id);
+            if queue.is_empty() {
+                self.requests.remove(&key);
+            }
+        }
+    }
+
+    fn dispatch_ack(&mut self, key: RequestKey, event: Event) {
+        let Some(queue) = self.requests.get_mut(&key) else {
+            return;
+        };
+        let Some(idx) = queue.iter().position(|r| !r.acked) else {
+            return;
+        };
+        match event {
+            Event::Ack => {
+                queue[idx].acked = true;
+                let _ = queue[idx].events.send(event);
+            }
+            _ => {
+                if let Some(request) = queue.remove(idx) {
+                    let _ = request.events.send(event);
+                }
+            }
+        }
+    }
+
+    fn dispatch_response(&mut self, key: RequestKey, data: Vec<u8>) {
+        let Some(queue) = self.requests.get_mut(&key) else {
+            return;
+        };
+        let Some(idx) = queue.iter().position(|r| r.acked) else {
+            return;
+        };
+        // The request stays outstanding until its final response.
+        let final_response = !is_response_pending(&queue[idx].request, &data);
+        let _ = queue[idx].events.send(Event::Response(data));
+        if final_response {
+            queue.remove(idx);
+        }
+    }
+
+    fn dispatch_header_nack(&mut self, nack_code: NegativeAckCode) {
+        for request in self.requests.values().flatten().filter(|r| !r.acked) {
+            let _ = request.events.send(Event::HeaderNack(nack_code));
+        }
+        if let Some(routing) = &self.routing {
+            let _ = routing.send(Err(nack_code));
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        for request in self.requests.values().flatten() {
+            let _ = request.events.send(Event::Closed);
+        }
+        self.requests.clear();
+        self.routing = None;
+    }
+}
+
+/// Removes a request from the outstanding ones when it completes, fails or is
+/// cancelled.
+struct PendingGuard<'a> {
+    pending: &'a Mutex<Pending>,
+    key: RequestKey,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(self.key, self.id);
+    }
+}
+
+/// Stops forwarding the routing activation responses when the routing
+/// activation completes, fails or is cancelled.
+struct RoutingGuard<'a> {
+    pending: &'a Mutex<Pending>,
+}
+
+impl Drop for RoutingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().routing = None;
+    }
+}
+
+/// Asynchronous DoIP tester client
+///
+/// This is the tokio counterpart of [`DoIpClient`](crate::DoIpClient), for a
+/// DoIP gateway fronting several DoIP entities. A background task reads all
+/// the messages of the connection, so that many tasks can send UDS requests
+/// concurrently with [`AsyncDoIpClient::send_diagnostic()`].
+///
+/// Outstanding requests are keyed by `(target_address, source_address)`. The
+/// [`DiagnosticMessagePositiveAck`], [`DiagnosticMessageNegativeAck`] and
+/// UDS response of a DoIP entity are paired with the oldest outstanding
+/// request to that entity. As with the [`DoIpClient`](crate::DoIpClient), each
+/// UDS response is acknowledged with a [`DiagnosticMessagePositiveAck`], and
+/// [`AliveCheckRequest`] messages are answered, by the background task.
+///
+/// Example:
+/// ```no_run
+/// use doip_rw::{message::ActivationType, AsyncDoIpClient};
+/// use std::sync::Arc;
+///
+/// # async fn run() -> Result<(), doip_rw::DoIpError> {
+/// let client = Arc::new(AsyncDoIpClient::connect("127.0.0.1:13400", 0x0e00).await?);
+/// client.activate_routing(ActivationType::Default).await?;
+/// let (a, b) = tokio::join!(
+///     client.send_diagnostic(0x0077, &[0x22, 0xf1, 0x90]),
+///     client.send_diagnostic(0x0078, &[0x22, 0xf1, 0x90]),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncDoIpClient {
+    source_address: LogicalAddress,
+    timeouts: ClientTimeouts,
+    writer: Arc<tokio::sync::Mutex<Writer>>,
+    pending: Arc<Mutex<Pending>>,
+    reader: JoinHandle<()>,
+}
+
+impl AsyncDoIpClient {
+    /// Connects to a DoIP entity, using `source_address` as the tester
+    /// logical address.
+    ///
+    /// This function must be called from within a tokio runtime, as it spawns
+    /// the background reader task.
+    pub async fn connect<A: ToSocketAddrs>(
+        addr: A,
+        source_address: LogicalAddress,
+    ) -> Result<Self, DoIpError> {
+        let stream = TcpStream::connect(addr).await?;
+        Self::from_stream(stream, source_address)
+    }
+
+    /// Creates a client from an already connected stream.
+    pub fn from_stream(
+        stream: TcpStream,
+        source_address: LogicalAddress,
+    ) -> Result<Self, DoIpError> {
+        Self::from_stream_with_codec(stream, source_address, DoIpCodec::new())
+    }
+
+    /// Creates a client from an already connected stream, decoding the
+    /// received messages with `codec`.
+    ///
+    /// The messages are sent with the protocol version of the codec, which
+    /// follows its [`VersionPolicy`](crate::VersionPolicy).
+    pub fn from_stream_with_codec(
+        stream: TcpStream,
+        source_address: LogicalAddress,
+        codec: DoIpCodec,
+    ) -> Result<Self, DoIpError> {
+        stream.set_nodelay(true)?;
+        let (read_half, write_half) = stream.into_split();
+        let writer = Arc::new(tokio::sync::Mutex::new(Writer {
+            stream: write_half,
+            version: codec.protocol_version(),
+        }));
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let reader = tokio::spawn(read_loop(
+            read_half,
+            codec,
+            source_address,
+            writer.clone(),
+            pending.clone(),
+        ));
+        Ok(Self {
+            source_address,
+            timeouts: ClientTimeouts::default(),
+            writer,
+            pending,
+            reader,
+        })
+    }
+
+    /// Replaces the default timeouts.
+    pub fn with_timeouts(mut self, timeouts: ClientTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Get the tester logical address.
+    pub fn source_address(&self) -> LogicalAddress {
+        self.source_address
+    }
+
+    /// Get the protocol version of the sent messages.
+    pub async fn protocol_version(&self) -> ProtocolVersion {
+        self.writer.lock().await.version
+    }
+
+    /// Activates the routing on the DoIP entity.
+    ///
+    /// See [`DoIpClient::activate_routing()`](crate::DoIpClient::activate_routing).
+    pub async fn activate_routing(
+        &self,
+        activation_type: ActivationType,
+    ) -> Result<RoutingActivationResponse, DoIpError> {
+        use RoutingActivationResponseCode::*;
+        let request = RoutingActivationRequest::builder(self.source_address)
+            .activation_type(activation_type)
+            .build();
+        let (sender, mut responses) = unbounded_channel();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.closed {
+                return Err(closed_error());
+            }
+            pending.routing = Some(sender);
+        }
+        let _guard = RoutingGuard {
+            pending: &self.pending,
+        };
+        let confirmation_deadline = Instant::now() + self.timeouts.routing_confirmation;
+        self.send(&request).await?;
+        let mut response = receive(&mut responses, Instant::now() + self.timeouts.ctrl)
+            .await?
+            .map_err(DoIpError::HeaderNegativeAck)?;
+        while response.routing_activation_response_code
+            == RoutingSuccessfullyActivatedConfirmationRequired
+        {
+            let deadline = confirmation_deadline.min(Instant::now() + self.timeouts.ctrl);
+            match receive(&mut responses, deadline).await {
+                Ok(r) => response = r.map_err(DoIpError::HeaderNegativeAck)?,
+                Err(DoIpError::Timeout) if Instant::now() < confirmation_deadline => {
+                    self.send(&request).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        match response.routing_activation_response_code {
+            RoutingSuccessfullyActivated => Ok(response),
+            code => Err(DoIpError::RoutingActivationDenied(code)),
+        }
+    }
+
+    /// Sends a UDS request to `target_address`, and returns the UDS response.
+    ///
+    /// See [`DoIpClient::send_diagnostic()`](crate::DoIpClient::send_diagnostic).
+    /// Several requests to the same DoIP entity are answered in order.
+    pub async fn send_diagnostic(
+        &self,
+        target_address: LogicalAddress,
+        data: &[u8],
+    ) -> Result<Vec<u8>, DoIpError> {
+        let key = (target_address, self.source_address);
+        let (id, mut events) = self.pending.lock().unwrap().push(key, data)?;
+        let _guard = PendingGuard {
+            pending: &self.pending,
+            key,
+            id,
+        };
+
+        let request = DiagnosticMessage {
+            source_address: self.source_address,
+            target_address,
+            user_data: UdsBuffer::Borrowed(data),
+        };
+        self.send(&request).await?;
+
+        match receive(&mut events, Instant::now() + self.timeouts.diagnostic_ack).await? {
+            Event::Ack => {}
+            event => return Err(event_error(event)),
+        }
+        let mut deadline = Instant::now() + self.timeouts.diagnostic_response;
+        loop {
+            match receive(&mut events, deadline).await? {
+                Event::Response(uds) if is_response_pending(data, &uds) => {
+                    deadline = Instant::now() + self.timeouts.diagnostic_response_pending;
+                }
+                Event::Response(uds) => return Ok(uds),
+                event => return Err(event_error(event)),
+            }
+        }
+    }
+
+    async fn send<P: Payload>(&self, payload: &P) -> Result<(), DoIpError> {
+        send(&self.writer, payload).await
+    }
+}
+
+impl Drop for AsyncDoIpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn send<P: Payload>(
+    writer: &tokio::sync::Mutex<Writer>,
+    payload: &P,
+) -> Result<(), DoIpError> {
+    let mut writer = writer.lock().await;
+    let mut buf = Vec::with_capacity(DOIP_HEADER_LENGTH + payload.length());
+    write_message_versioned(writer.version, payload, &mut buf)?;
+    writer.stream.write_all(&buf).await?;
+    Ok(())
+}
+
+async fn receive<T>(events: &mut UnboundedReceiver<T>, deadline: Instant) -> Result<T, DoIpError> {
+    match timeout_at(deadline, events.recv()).await {
+        Ok(Some(event)) => Ok(event),
+        Ok(None) => Err(closed_error()),
+        Err(_) => Err(DoIpError::Timeout),
+    }
+}
+
+fn closed_error() -> DoIpError {
+    std::io::Error::from(ErrorKind::ConnectionAborted).into()
+}
+
+fn event_error(event: Event) -> DoIpError {
+    match event {
+        Event::Nack(code) => DoIpError::DiagnosticMessageNegativeAck(code),
+        Event::HeaderNack(code) => DoIpError::HeaderNegativeAck(code),
+        _ => closed_error(),
+    }
+}
+
+async fn read_loop(
+    mut reader: OwnedReadHalf,
+    mut codec: DoIpCodec,
+    source_address: LogicalAddress,
+    writer: Arc<tokio::sync::Mutex<Writer>>,
+    pending: Arc<Mutex<Pending>>,
+) {
+    let _ = read_messages(&mut reader, &mut codec, source_address, &writer, &pending).await;
+    pending.lock().unwrap().close();
+}
+
+async fn read_messages(
+    reader: &mut OwnedReadHalf,
+    codec: &mut DoIpCodec,
+    source_address: LogicalAddress,
+    writer: &tokio::sync::Mutex<Writer>,
+    pending: &Mutex<Pending>,
+) -> Result<(), DoIpError> {
+    let mut buf = BytesMut::with_capacity(4096);
+    loop {
+        let message = match codec.decode(&mut buf) {
+            Ok(Some((_, message))) => message,
+            Ok(None) => {
+                if reader.read_buf(&mut buf).await? == 0 {
+                    return Ok(());
+                }
+                continue;
+            }
+            // The codec discards the payload, the next messages can be read.
+            Err(DoIpError::MessageTooLarge { .. } | DoIpError::UnknownPayloadType(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        writer.lock().await.version = codec.protocol_version();
+        match message {
+            DoIpMessage::AliveCheckRequest(_) => {
+                send(writer, &AliveCheckResponse { source_address }).await?;
+            }
+            DoIpMessage::DiagnosticMessage(ref response) => {
+                let ack =
+                    DiagnosticMessagePositiveAck::new(source_address, response.source_address);
+                send(writer, &ack).await?;
+                dispatch(pending, message);
+            }
+            message => dispatch(pending, message),
+        }
+    }
+}
+
+fn dispatch(pending: &Mutex<Pending>, message: DoIpMessage) {
+    let mut pending = pending.lock().unwrap();
+    match message {
+        DoIpMessage::DiagnosticMessagePositiveAck(ack) => {
+            let key = (ack.source_address, ack.target_address);
+            pending.dispatch_ack(key, Event::Ack);
+        }
+        DoIpMessage::DiagnosticMessageNegativeAck(nack) => {
+            let key = (nack.source_address, nack.target_address);
+            pending.dispatch_ack(key, Event::Nack(nack.ack_code));
+        }
+        DoIpMessage::DiagnosticMessage(response) => {
+            let key = (response.source_address, response.target_address);
+            let data = match response.user_data {
+                UdsBuffer::Owned(v) => v,
+                UdsBuffer::Borrowed(b) => b.to_vec(),
+            };
+            pending.dispatch_response(key, data);
+        }
+        DoIpMessage::RoutingActivationResponse(response) => {
+            if let Some(routing) = &pending.routing {
+                let _ = routing.send(Ok(response));
+            }
+        }
+        DoIpMessage::GenericDoIpHeaderNegativeAcknowledge(nack) => {
+            pending.dispatch_header_nack(nack.nack_code);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::VersionPolicy;
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_util::codec::Framed;
+
+    const TESTER: LogicalAddress = 0x0e00;
+
+    async fn gateway() -> (AsyncDoIpClient, Framed<TcpStream, DoIpCodec>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = AsyncDoIpClient::connect(addr, TESTER).await.unwrap();
+        let (tcp, _) = listener.accept().await.unwrap();
+        let timeouts = ClientTimeouts {
+            diagnostic_ack: Duration::from_millis(500),
+            diagnostic_response: Duration::from_millis(500),
+            ctrl: Duration::from_millis(500),
+            ..ClientTimeouts::default()
+        };
+        (
+            client.with_timeouts(timeouts),
+            Framed::new(tcp, DoIpCodec::new()),
+        )
+    }
+
+    fn ack(entity: LogicalAddress) -> DiagnosticMessagePositiveAck<'static> {
+        DiagnosticMessagePositiveAck {
+            source_address: entity,
+            target_address: TESTER,
+            ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
+            previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+        }
+    }
+
+    fn uds(entity: LogicalAddress, data: Vec<u8>) -> DiagnosticMessage<'static> {
+        DiagnosticMessage {
+            source_address: entity,
+            target_address: TESTER,
+            user_data: UdsBuffer::Owned(data),
+        }
+    }
+
+    async fn next_request(gw: &mut Framed<TcpStream, DoIpCodec>) -> DiagnosticMessage<'static> {
+        match gw.next().await.unwrap().unwrap().1 {
+            DoIpMessage::DiagnosticMessage(request) => request,
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
+    #[tokio::test]
+    async fn multiplexed_requests() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let r1 = tokio::spawn(async move { c1.send_diagnostic(0x0001, &[0x22, 0x01]).await });
+        let c2 = client.clone();
+        let r2 = tokio::spawn(async move { c2.send_diagnostic(0x0002, &[0x22, 0x02]).await });
+
+        let mut requests = [next_request(&mut gw).await, next_request(&mut gw).await];
+        requests.sort_by_key(|r| r.target_address);
+        gw.send(&AliveCheckRequest {}).await.unwrap();
+        match gw.next().await.unwrap().unwrap().1 {
+            DoIpMessage::AliveCheckResponse(r) => assert_eq!(r.source_address, TESTER),
+            message => panic!("Unexpected message {:?}", message),
+        }
+        // Answer in reverse order, with a response pending for the first one.
+        gw.send(&ack(0x0002)).await.unwrap();
+        gw.send(&ack(0x0001)).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x7f, 0x22, 0x78])).await.unwrap();
+        gw.send(&uds(0x0002, vec![0x62, 0x02])).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x62, 0x01])).await.unwrap();
+
+        assert_eq!(r1.await.unwrap().unwrap(), vec![0x62, 0x01]);
+        assert_eq!(r2.await.unwrap().unwrap(), vec![0x62, 0x02]);
+        assert_eq!(requests[0].user_data.get_ref(), &[0x22, 0x01]);
+        assert_eq!(requests[1].user_data.get_ref(), &[0x22, 0x02]);
+    }
+
+    #[tokio::test]
+    async fn same_target_in_order() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let r1 = tokio::spawn(async move { c1.send_diagnostic(0x0001, &[0x3e, 0x00]).await });
+        next_request(&mut gw).await;
+        let c2 = client.clone();
+        let r2 = tokio::spawn(async move { c2.send_diagnostic(0x0001, &[0x22, 0x01]).await });
+        next_request(&mut gw).await;
+
+        gw.send(&ack(0x0001)).await.unwrap();
+        gw.send(&ack(0x0001)).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x7e, 0x00])).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x62, 0x01])).await.unwrap();
+        assert_eq!(r1.await.unwrap().unwrap(), vec![0x7e, 0x00]);
+        assert_eq!(r2.await.unwrap().unwrap(), vec![0x62, 0x01]);
+        for _ in 0..2 {
+            match gw.next().await.unwrap().unwrap().1 {
+                DoIpMessage::DiagnosticMessagePositiveAck(ack) => {
+                    assert_eq!((ack.source_address, ack.target_address), (TESTER, 0x0001));
+                }
+                message => panic!("Unexpected message {:?}", message),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn response_pending_other_service() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let r1 = tokio::spawn(async move { c1.send_diagnostic(0x0001, &[0x22, 0x01]).await });
+        next_request(&mut gw).await;
+
+        gw.send(&ack(0x0001)).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x7f, 0x31, 0x78])).await.unwrap();
+        assert_eq!(r1.await.unwrap().unwrap(), vec![0x7f, 0x31, 0x78]);
+    }
+
+    #[tokio::test]
+    async fn routing_activation_timeout() {
+        let (client, mut gw) = gateway().await;
+        let res = client.activate_routing(ActivationType::Default).await;
+        assert!(matches!(res, Err(DoIpError::Timeout)));
+        assert!(matches!(
+            gw.next().await.unwrap().unwrap().1,
+            DoIpMessage::RoutingActivationRequest(_)
+        ));
+        assert!(client.pending.lock().unwrap().routing.is_none());
+    }
+
+    #[tokio::test]
+    async fn skips_unknown_payload_type() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let r1 = tokio::spawn(async move { c1.send_diagnostic(0x0001, &[0x3e, 0x00]).await });
+        next_request(&mut gw).await;
+
+        gw.get_mut()
+            .write_all(&[0x02, 0xfd, 0x12, 0x34, 0x00, 0x00, 0x00, 0x02, 0xaa, 0xbb])
+            .await
+            .unwrap();
+        gw.send(&ack(0x0001)).await.unwrap();
+        gw.send(&uds(0x0001, vec![0x7e, 0x00])).await.unwrap();
+        assert_eq!(r1.await.unwrap().unwrap(), vec![0x7e, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn routing_activation_header_nack() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let res = tokio::spawn(async move { c1.activate_routing(ActivationType::Default).await });
+        assert!(matches!(
+            gw.next().await.unwrap().unwrap().1,
+            DoIpMessage::RoutingActivationRequest(_)
+        ));
+        let nack = GenericDoIpHeaderNegativeAcknowledge {
+            nack_code: NegativeAckCode::MessageTooLarge,
+        };
+        gw.send(&nack).await.unwrap();
+        let start = Instant::now();
+        assert!(matches!(
+            res.await.unwrap(),
+            Err(DoIpError::HeaderNegativeAck(
+                NegativeAckCode::MessageTooLarge
+            ))
+        ));
+        assert!(start.elapsed() < Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn negative_ack() {
+        let (client, mut gw) = gateway().await;
+        let client = Arc::new(client);
+        let c1 = client.clone();
+        let r1 = tokio::spawn(async move { c1.send_diagnostic(0x0001, &[0x3e, 0x00]).await });
+        next_request(&mut gw).await;
+        let nack = DiagnosticMessageNegativeAck {
+            source_address: 0x0001,
+            target_address: TESTER,
+            ack_code: DiagnosticMessageNegativeAckCode::TargetUnreachable,
+            previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+        };
+        gw.send(&nack).await.unwrap();
+        assert!(matches!(
+            r1.await.unwrap(),
+            Err(DoIpError::DiagnosticMessageNegativeAck(
+                DiagnosticMessageNegativeAckCode::TargetUnreachable
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn timeout_and_close() {
+        let (client, mut gw) = gateway().await;
+        let res = client.send_diagnostic(0x0001, &[0x3e, 0x00]).await;
+        assert!(matches!(res, Err(DoIpError::Timeout)));
+        next_request(&mut gw).await;
+
+        drop(gw);
+        let res = client.send_diagnostic(0x0001, &[0x3e, 0x00]).await;
+        assert!(matches!(res, Err(DoIpError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn version_echo() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let codec =
+            DoIpCodec::new().with_version_policy(VersionPolicy::Echo(ProtocolVersion::DoIpIso));
+        let client = AsyncDoIpClient::from_stream_with_codec(tcp, TESTER, codec).unwrap();
+        let (tcp, _) = listener.accept().await.unwrap();
+        let gw_codec = DoIpCodec::new()
+            .with_version_policy(VersionPolicy::Fixed(ProtocolVersion::Iso13400_2019));
+        let mut gw = Framed::new(tcp, gw_codec);
+
+        assert_eq!(client.protocol_version().await, ProtocolVersion::DoIpIso);
+        gw.send(&AliveCheckRequest {}).await.unwrap();
+        let (header, message) = gw.next().await.unwrap().unwrap();
+        assert!(matches!(message, DoIpMessage::AliveCheckResponse(_)));
+        assert_eq!(
+            header.protocol_version,
+            ProtocolVersion::Iso13400_2019 as u8
+        );
+        assert_eq!(
+            client.protocol_version().await,
+            ProtocolVersion::Iso13400_2019
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use std::io::ErrorKind;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::message::*;
+use crate::VersionPolicy;
+use crate::{read_any_payload, read_header_validated, write_message_versioned};
+use crate::{DoIpError, DoIpMessage, DoIpStream, HeaderValidator, LogicalAddress, Payload};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Timeouts used by [`DoIpClient`].
+///
+/// The default values are the ones of ISO 13400-2 for the DoIP timings, and
+/// the usual UDS P2 server timings for the responses.
+pub struct ClientTimeouts {
+    /// Maximum time to wait for the response to a control message, ie.
+    /// A_DoIP_Ctrl (2s).
+    pub ctrl: Duration,
+    /// Maximum time to wait for a diagnostic message acknowledgement, ie.
+    /// A_DoIP_Diagnostic_Message (2s).
+    pub diagnostic_ack: Duration,
+    /// Maximum time to wait for the UDS response once the diagnostic message
+    /// was acknowledged (2s).
+    pub diagnostic_response: Duration,
+    /// Maximum time to wait for the next UDS response after a "response
+    /// pending" negative response (5s).
+    pub diagnostic_response_pending: Duration,
+    /// Maximum time to wait for the routing activation confirmation, when the
+    /// DoIP entity requires it (10s).
+    pub routing_confirmation: Duration,
+}
+
+impl Default for ClientTimeouts {
+    fn default() -> Self {
+        Self {
+            ctrl: Duration::from_secs(2),
+            diagnostic_ack: Duration::from_secs(2),
+            diagnostic_response: Duration::from_secs(2),
+            diagnostic_response_pending: Duration::from_secs(5),
+            routing_confirmation: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Synchronous DoIP tester client
+///
+/// The client owns the TCP connection to a DoIP entity, in clear or secured
+/// with TLS, and sequences the messages exchanged by an external tester :
+/// - [`DoIpClient::activate_routing()`] for the routing activation,
+/// - [`DoIpClient::send_diagnostic()`] for each UDS request.
+///
+/// While waiting for a response, [`AliveCheckRequest`] messages from the DoIP
+/// entity are automatically answered.
+///
+/// After an error, and especially after a [`DoIpError::Timeout`], the
+/// connection might be in the middle of a message, and should be dropped.
+///
+/// Example:
+/// ```no_run
+/// use doip_rw::{message::ActivationType, DoIpClient};
+///
+/// let mut client = DoIpClient::connect("127.0.0.1:13400", 0x0e00)?;
+/// client.activate_routing(ActivationType::Default)?;
+/// let response = client.send_diagnostic(0x0077, &[0x22, 0xf1, 0x90])?;
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+///
+/// With the `tls` feature, a DoIP entity denying the routing activation with
+/// [`RoutingActivationResponseCode::RoutingActivationDeniedEncryptedConnectionViaTLSRequired`]
+/// is reached again on [`crate::TLS_PORT`] through a [`crate::TlsStream`]:
+/// ```no_run
+/// # #[cfg(feature = "tls")]
+/// # {
+/// # use std::sync::Arc;
+/// use doip_rw::{message::ActivationType, DoIpClient, TlsStream, TLS_PORT};
+///
+/// # fn config() -> Arc<rustls::ClientConfig> { unimplemented!() }
+/// let mut client = DoIpClient::connect("192.168.0.10:13400", 0x0e00)?;
+/// if let Err(e) = client.activate_routing(ActivationType::Default) {
+///     if !e.requires_tls() {
+///         return Err(e);
+///     }
+///     let server_name = "ecu.vehicle".try_into().unwrap();
+///     let stream = TlsStream::connect(("192.168.0.10", TLS_PORT), server_name, config())?;
+///     let mut client = DoIpClient::from_stream(stream, 0x0e00)?;
+///     client.activate_routing(ActivationType::Default)?;
+/// }
+/// # }
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub struct DoIpClient<S = TcpStream> {
+    stream: S,
+    source_address: LogicalAddress,
+    timeouts: ClientTimeouts,
+    validator: HeaderValidator,
+    version: VersionPolicy,
+}
+
+impl DoIpClient {
+    /// Connects to a DoIP entity, using `source_address` as the tester
+    /// logical address.
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        source_address: LogicalAddress,
+    ) -> Result<Self, DoIpError> {
+        let stream = TcpStream::connect(addr)?;
+        Self::from_stream(stream, source_address)
+    }
+}
+
+impl<S: DoIpStream> DoIpClient<S> {
+    /// Creates a client from an already connected stream.
+    pub fn from_stream(stream: S, source_address: LogicalAddress) -> Result<Self, DoIpError> {
+        stream.tcp_stream().set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            source_address,
+            timeouts: ClientTimeouts::default(),
+            validator: HeaderValidator::default(),
+            version: VersionPolicy::default(),
+        })
+    }
+
+    /// Replaces the default timeouts.
+    pub fn with_timeouts(mut self, timeouts: ClientTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Replaces the default header validator of received messages.
+    pub fn with_validator(mut self, validator: HeaderValidator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Replaces the default protocol version policy, ie. always sending
+    /// [`ProtocolVersion::DoIpIso`].
+    pub fn with_version_policy(mut self, version: VersionPolicy) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Get the protocol version of the sent messages.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.version.version()
+    }
+
+    /// Get the tester logical address.
+    pub fn source_address(&self) -> LogicalAddress {
+        self.source_address
+    }
+
+    /// Get the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Activates the routing on the DoIP entity.
+    ///
+    /// If the DoIP entity requires a confirmation, the routing activation
+    /// request is repeated until the routing is activated or denied, or until
+    /// [`ClientTimeouts::routing_confirmation`] expires.
+    ///
+    /// Any response code other than
+    /// [`RoutingActivationResponseCode::RoutingSuccessfullyActivated`] is
+    /// returned as [`DoIpError::RoutingActivationDenied`].
+    pub fn activate_routing(
+        &mut self,
+        activation_type: ActivationType,
+    ) -> Result<RoutingActivationResponse, DoIpError> {
+        use RoutingActivationResponseCode::*;
+        let request = RoutingActivationRequest::builder(self.source_address)
+            .activation_type(activation_type)
+            .build();
+        let confirmation_deadline = Instant::now() + self.timeouts.routing_confirmation;
+        self.send(&request)?;
+        let mut response = self.receive_routing_activation(Instant::now() + self.timeouts.ctrl)?;
+        while response.routing_activation_response_code
+            == RoutingSuccessfullyActivatedConfirmationRequired
+        {
+            let deadline = confirmation_deadline.min(Instant::now() + self.timeouts.ctrl);
+            match self.receive_routing_activation(deadline) {
+                Ok(r) => response = r,
+                Err(DoIpError::Timeout) if Instant::now() < confirmation_deadline => {
+                    self.send(&request)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        match response.routing_activation_response_code {
+            RoutingSuccessfullyActivated => Ok(response),
+            code => Err(DoIpError::RoutingActivationDenied(code)),
+        }
+    }
+
+    /// Sends a UDS request to `target_address`, and returns the UDS response.
+    ///
+    /// The function waits for the [`DiagnosticMessagePositiveAck`] of the
+    /// request, then for the [`DiagnosticMessage`] carrying the UDS response,
+    /// which is acknowledged in turn. A "response pending" UDS negative
+    /// response (NRC 0x78) is not returned, and the function keeps on waiting
+    /// for the final response.
+    ///
+    /// A [`DiagnosticMessageNegativeAck`] is returned as
+    /// [`DoIpError::DiagnosticMessageNegativeAck`].
+    pub fn send_diagnostic(
+        &mut self,
+        target_address: LogicalAddress,
+        data: &[u8],
+    ) -> Result<Vec<u8>, DoIpError> {
+        let request = DiagnosticMessage {
+            source_address: self.source_address,
+            target_address,
+            user_data: UdsBuffer::Borrowed(data),
+        };
+        self.send(&request)?;
+
+        let deadline = Instant::now() + self.timeouts.diagnostic_ack;
+        loop {
+            match self.receive(deadline)? {
+                DoIpMessage::DiagnosticMessagePositiveAck(ack)
+                    if self.is_from(target_address, ack.source_address, ack.target_address) =>
+                {
+                    break
+                }
+                DoIpMessage::DiagnosticMessageNegativeAck(nack)
+                    if self.is_from(target_address, nack.source_address, nack.target_address) =>
+                {
+                    return Err(DoIpError::DiagnosticMessageNegativeAck(nack.ack_code))
+                }
+                _ => continue,
+            }
+        }
+
+        let mut deadline = Instant::now() + self.timeouts.diagnostic_response;
+        loop {
+            let response = match self.receive(deadline)? {
+                DoIpMessage::DiagnosticMessage(response)
+                    if self.is_from(
+                        target_address,
+                        response.source_address,
+                        response.target_address,
+                    ) =>
+                {
+                    response
+                }
+                _ => continue,
+            };
+            let ack = DiagnosticMessagePositiveAck::new(self.source_address, target_address);
+            self.send(&ack)?;
+            let uds = match response.user_data {
+                UdsBuffer::Owned(v) => v,
+                UdsBuffer::Borrowed(b) => b.to_vec(),
+            };
+            if is_response_pending(data, &uds) {
+                deadline = Instant::now() + self.timeouts.diagnostic_response_pending;
+                continue;
+            }
+            return Ok(uds);
+        }
+    }
+
+    fn is_from(
+        &self,
+        target_address: LogicalAddress,
+        source: LogicalAddress,
+        target: LogicalAddress,
+    ) -> bool {
+        source == target_address && target == self.source_address
+    }
+
+    fn receive_routing_activation(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<RoutingActivationResponse, DoIpError> {
+        loop {
+            if let DoIpMessage::RoutingActivationResponse(response) = self.receive(deadline)? {
+                return Ok(response);
+            }
+        }
+    }
+
+    fn receive(&mut self, deadline: Instant) -> Result<DoIpMessage<'static>, DoIpError> {
+        loop {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|d| !d.is_zero())
+                .ok_or(DoIpError::Timeout)?;
+            self.stream.set_read_timeout(Some(remaining))?;
+            let message = read_header_validated(&mut self.stream, &self.validator)
+                .and_then(|header| {
+                    self.version.learn(&header);
+                    read_any_payload(&mut self.stream, &header)
+                })
+                .map_err(|e| match e {
+                    DoIpError::Io(e)
+                        if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                    {
+                        DoIpError::Timeout
+                    }
+                    e => e,
+                })?;
+            match message {
+                DoIpMessage::AliveCheckRequest(_) => {
+                    let response = AliveCheckResponse {
+                        source_address: self.source_address,
+                    };
+                    self.send(&response)?;
+                }
+                DoIpMessage::GenericDoIpHeaderNegativeAcknowledge(nack) => {
+                    return Err(DoIpError::HeaderNegativeAck(nack.nack_code))
+                }
+                message => return Ok(message),
+            }
+        }
+    }
+
+    fn send<P: Payload>(&mut self, payload: &P) -> Result<(), DoIpError> {
+        write_message_versioned(self.version.version(), payload, &mut self.stream)
+    }
+}
+
+pub(crate) fn is_response_pending(request: &[u8], response: &[u8]) -> bool {
+    const NEGATIVE_RESPONSE: u8 = 0x7f;
+    const RESPONSE_PENDING: u8 = 0x78;
+    matches!(response, [NEGATIVE_RESPONSE, sid, RESPONSE_PENDING] if request.first() == Some(sid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{read_any_message, read_header, read_message, write_message};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const TESTER: LogicalAddress = 0x0e00;
+    const ENTITY: LogicalAddress = 0x0077;
+
+    fn entity<F>(script: F) -> (DoIpClient, thread::JoinHandle<()>)
+    where
+        F: FnOnce(TcpStream) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            script(tcp);
+        });
+        let timeouts = ClientTimeouts {
+            ctrl: Duration::from_millis(200),
+            diagnostic_ack: Duration::from_millis(200),
+            diagnostic_response: Duration::from_millis(200),
+            diagnostic_response_pending: Duration::from_millis(500),
+            routing_confirmation: Duration::from_millis(1000),
+        };
+        let client = DoIpClient::connect(addr, TESTER)
+            .unwrap()
+            .with_timeouts(timeouts);
+        (client, handle)
+    }
+
+    fn routing_response(code: RoutingActivationResponseCode) -> RoutingActivationResponse {
+        RoutingActivationResponse {
+            logical_address_tester: TESTER,
+            logical_address_of_doip_entity: ENTITY,
+            routing_activation_response_code: code,
+            reserved_oem: [0; 4],
+            oem_specific: None,
+        }
+    }
+
+    fn ack() -> DiagnosticMessagePositiveAck<'static> {
+        DiagnosticMessagePositiveAck {
+            source_address: ENTITY,
+            target_address: TESTER,
+            ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
+            previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+        }
+    }
+
+    fn uds(data: &[u8]) -> DiagnosticMessage<'_> {
+        DiagnosticMessage {
+            source_address: ENTITY,
+            target_address: TESTER,
+            user_data: UdsBuffer::Borrowed(data),
+        }
+    }
+
+    #[test]
+    fn routing_activation_with_confirmation() {
+        use RoutingActivationResponseCode::*;
+        let (mut client, entity) = entity(|mut tcp| {
+            let rareq: RoutingActivationRequest = read_message(&mut tcp).unwrap();
+            assert_eq!(rareq.source_address, TESTER);
+            let rsp = routing_response(RoutingSuccessfullyActivatedConfirmationRequired);
+            write_message(&rsp, &mut tcp).unwrap();
+            let _: RoutingActivationRequest = read_message(&mut tcp).unwrap();
+            write_message(&routing_response(RoutingSuccessfullyActivated), &mut tcp).unwrap();
+        });
+        let rsp = client.activate_routing(ActivationType::Default).unwrap();
+        assert_eq!(rsp.logical_address_of_doip_entity, ENTITY);
+        entity.join().unwrap();
+    }
+
+    #[test]
+    fn routing_activation_denied() {
+        use RoutingActivationResponseCode::*;
+        let (mut client, entity) = entity(|mut tcp| {
+            let _: RoutingActivationRequest = read_message(&mut tcp).unwrap();
+            let rsp = routing_response(RoutingActivationDeniedUnknownSourceAddress);
+            write_message(&rsp, &mut tcp).unwrap();
+        });
+        let err = client
+            .activate_routing(ActivationType::Default)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DoIpError::RoutingActivationDenied(RoutingActivationDeniedUnknownSourceAddress)
+        ));
+        entity.join().unwrap();
+    }
+
+    #[test]
+    fn send_diagnostic() {
+        let (mut client, entity) = entity(|mut tcp| {
+            let req: DiagnosticMessage = read_message(&mut tcp).unwrap();
+            assert_eq!(req.target_address, ENTITY);
+            assert_eq!(req.user_data.get_ref(), &[0x22, 0xf1, 0x90]);
+            write_message(&AliveCheckRequest {}, &mut tcp).unwrap();
+            let alive: AliveCheckResponse = read_message(&mut tcp).unwrap();
+            assert_eq!(alive.source_address, TESTER);
+            write_message(&ack(), &mut tcp).unwrap();
+            write_message(&uds(&[0x7f, 0x22, 0x78]), &mut tcp).unwrap();
+            let _: DiagnosticMessagePositiveAck = read_message(&mut tcp).unwrap();
+            write_message(&uds(&[0x62, 0xf1, 0x90, 0x01]), &mut tcp).unwrap();
+            let _: DiagnosticMessagePositiveAck = read_message(&mut tcp).unwrap();
+        });
+        let rsp = client.send_diagnostic(ENTITY, &[0x22, 0xf1, 0x90]).unwrap();
+        assert_eq!(rsp, vec![0x62, 0xf1, 0x90, 0x01]);
+        entity.join().unwrap();
+    }
+
+    #[test]
+    fn send_diagnostic_nack() {
+        let (mut client, entity) = entity(|mut tcp| {
+            let _: DiagnosticMessage = read_message(&mut tcp).unwrap();
+            let nack = DiagnosticMessageNegativeAck {
+                source_address: ENTITY,
+                target_address: TESTER,
+                ack_code: DiagnosticMessageNegativeAckCode::UnknownTargetAddress,
+                previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+            };
+            write_message(&nack, &mut tcp).unwrap();
+        });
+        let err = client.send_diagnostic(ENTITY, &[0x3e, 0x00]).unwrap_err();
+        assert!(matches!(
+            err,
+            DoIpError::DiagnosticMessageNegativeAck(
+                DiagnosticMessageNegativeAckCode::UnknownTargetAddress
+            )
+        ));
+        entity.join().unwrap();
+    }
+
+    #[test]
+    fn send_diagnostic_timeout() {
+        let (mut client, entity) = entity(|mut tcp| {
+            let _: DiagnosticMessage = read_message(&mut tcp).unwrap();
+            write_message(&ack(), &mut tcp).unwrap();
+            // No UDS response, wait for the client to give up.
+            assert!(read_any_message(&mut tcp).is_err());
+        });
+        let err = client.send_diagnostic(ENTITY, &[0x3e, 0x00]).unwrap_err();
+        assert!(matches!(err, DoIpError::Timeout));
+        drop(client);
+        entity.join().unwrap();
+    }
+    #[test]
+    fn version_echo() {
+        let (client, handle) = entity(|mut tcp| {
+            let header = read_header(&mut tcp).unwrap();
+            assert_eq!(header.protocol_version, ProtocolVersion::DoIpIso as u8);
+            read_any_payload(&mut tcp, &header).unwrap();
+            let response =
+                routing_response(RoutingActivationResponseCode::RoutingSuccessfullyActivated);
+            write_message_versioned(ProtocolVersion::Iso13400_2019, &response, &mut tcp).unwrap();
+
+            let header = read_header(&mut tcp).unwrap();
+            assert_eq!(
+                header.protocol_version,
+                ProtocolVersion::Iso13400_2019 as u8
+            );
+            read_any_payload(&mut tcp, &header).unwrap();
+            write_message_versioned(ProtocolVersion::Iso13400_2019, &ack(), &mut tcp).unwrap();
+            let response = uds(&[0x7e, 0x00]);
+            write_message_versioned(ProtocolVersion::Iso13400_2019, &response, &mut tcp).unwrap();
+            let header = read_header(&mut tcp).unwrap();
+            assert_eq!(
+                header.protocol_version,
+                ProtocolVersion::Iso13400_2019 as u8
+            );
+            read_any_payload(&mut tcp, &header).unwrap();
+        });
+        let mut client = client.with_version_policy(VersionPolicy::Echo(ProtocolVersion::DoIpIso));
+        client.activate_routing(ActivationType::Default).unwrap();
+        assert_eq!(client.protocol_version(), ProtocolVersion::Iso13400_2019);
+        assert_eq!(
+            client.send_diagnostic(ENTITY, &[0x3e, 0x00]).unwrap(),
+            vec![0x7e, 0x00]
+        );
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::message::ProtocolVersion;
+use crate::{write_any_message_versioned, write_message_versioned, VersionPolicy};
+use crate::{DoIpError, DoIpHeader, DoIpMessage, HeaderValidator, Payload, DOIP_HEADER_LENGTH};
+
+#[derive(Debug, Default)]
+/// DoIP frames codec
+///
+/// This codec splits a byte stream into DoIP messages, each one being a
+/// [`DoIpHeader`] and its decoded [`DoIpMessage`]. It is meant to be used with
+/// the `tokio_util` framing, ie. `Framed<TcpStream, DoIpCodec>`.
+///
+/// Each received header is checked with a [`HeaderValidator`] before the
+/// payload is buffered. When the header is rejected with
+/// [`DoIpError::UnknownPayloadType`] or [`DoIpError::MessageTooLarge`], the
+/// payload is discarded by the codec, so that the next message can still be
+/// decoded. Other errors leave the stream in an unknown state, and the
+/// connection should be closed.
+///
+/// Encoding is provided both for [`DoIpMessage`] and for any [`Payload`]. The
+/// protocol version of the encoded messages follows the codec
+/// [`VersionPolicy`], so that a codec created with
+/// [`VersionPolicy::Echo`] answers with the version of the first decoded
+/// message.
+///
+/// Example:
+/// ```no_run
+/// use doip_rw::{DoIpCodec, DoIpMessage, message::AliveCheckResponse};
+/// use futures::{SinkExt, StreamExt};
+/// use tokio::net::TcpStream;
+/// use tokio_util::codec::Framed;
+///
+/// # async fn run() -> Result<(), doip_rw::DoIpError> {
+/// let tcp = TcpStream::connect("127.0.0.1:13400").await?;
+/// let mut framed = Framed::new(tcp, DoIpCodec::new());
+/// while let Some(frame) = framed.next().await {
+///     let (_header, message) = frame?;
+///     if let DoIpMessage::AliveCheckRequest(_) = message {
+///         framed.send(&AliveCheckResponse { source_address: 0x0e00 }).await?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DoIpCodec {
+    validator: HeaderValidator,
+    header: Option<DoIpHeader>,
+    discard: usize,
+    version: VersionPolicy,
+}
+
+impl DoIpCodec {
+    /// Creates a [`DoIpCodec`] with the default [`HeaderValidator`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`DoIpCodec`] validating headers with `validator`.
+    pub fn with_validator(validator: HeaderValidator) -> Self {
+        Self {
+            validator,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the default protocol version policy, ie. always encoding
+    /// with [`ProtocolVersion::DoIpIso`].
+    pub fn with_version_policy(mut self, version: VersionPolicy) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Get the protocol version of the encoded messages.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.version.version()
+    }
+}
+
+impl Decoder for DoIpCodec {
+    type Item = (DoIpHeader, DoIpMessage<'static>);
+    type Error = DoIpError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if self.discard > 0 {
+            let nb = self.discard.min(src.len());
+            src.advance(nb);
+            self.discard -= nb;
+            if self.discard > 0 {
+                return Ok(None);
+            }
+        }
+
+        let header = match self.header.take() {
+            Some(header) => header,
+            None => {
+                let Some(header_bytes) = src.first_chunk() else {
+                    src.reserve(DOIP_HEADER_LENGTH - src.len());
+                    return Ok(None);
+                };
+                let header = DoIpHeader::from_bytes(header_bytes);
+                if let Err(e) = self.validator.validate(&header) {
+                    if matches!(
+                        e,
+                        DoIpError::UnknownPayloadType(_) | DoIpError::MessageTooLarge { .. }
+                    ) {
+                        src.advance(DOIP_HEADER_LENGTH);
+                        self.discard = header.payload_length as usize;
+                    }
+                    return Err(e);
+                }
+                src.advance(DOIP_HEADER_LENGTH);
+                self.version.learn(&header);
+                header
+            }
+        };
+
+        let payload_length = header.payload_length as usize;
+        if src.len() < payload_length {
+            src.reserve(payload_length - src.len());
+            self.header = Some(header);
+            return Ok(None);
+        }
+        let payload = src.split_to(payload_length);
+        let message = DoIpMessage::read(&mut &payload[..], header.payload_type, payload_length)?;
+        Ok(Some((header, message)))
+    }
+}
+
+impl Encoder<&DoIpMessage<'_>> for DoIpCodec {
+    type Error = DoIpError;
+
+    fn encode(&mut self, item: &DoIpMessage<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(DOIP_HEADER_LENGTH + item.length());
+        write_any_message_versioned(self.version.version(), item, &mut dst.writer())
+    }
+}
+
+impl Encoder<DoIpMessage<'_>> for DoIpCodec {
+    type Error = DoIpError;
+
+    fn encode(&mut self, item: DoIpMessage<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&item, dst)
+    }
+}
+
+impl<P: Payload> Encoder<&P> for DoIpCodec {
+    type Error = DoIpError;
+
+    fn encode(&mut self, item: &P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(DOIP_HEADER_LENGTH + item.length());
+        write_message_versioned(self.version.version(), item, &mut dst.writer())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{write_message, PayloadType};
+
+    fn encoded<P: Payload>(payload: &P) -> Vec<u8> {
+        let mut v = vec![];
+        write_message(payload, &mut v).unwrap();
+        v
+    }
+
+    #[test]
+    fn decode_partial_frames() {
+        let diag = DiagnosticMessage {
+            source_address: 0x0123,
+            target_address: 0x00ed,
+            user_data: UdsBuffer::Owned(vec![0x22, 0xf0, 0x12]),
+        };
+        let mut stream = encoded(&diag);
+        stream.extend(encoded(&AliveCheckRequest {}));
+
+        let mut codec = DoIpCodec::new();
+        let mut src = BytesMut::new();
+        let mut frames = vec![];
+        for b in stream {
+            src.put_u8(b);
+            if let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert!(src.is_empty());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].0.payload_type, PayloadType::DiagnosticMessage);
+        assert_eq!(frames[0].1, DoIpMessage::DiagnosticMessage(diag));
+        assert_eq!(
+            frames[1].1,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+    }
+
+    #[test]
+    fn decode_discards_too_large() {
+        let diag = DiagnosticMessage {
+            source_address: 0x0123,
+            target_address: 0x00ed,
+            user_data: UdsBuffer::Owned(vec![0x36; 32]),
+        };
+        let mut codec = DoIpCodec::with_validator(HeaderValidator::new(16));
+        let mut src = BytesMut::from(&encoded(&diag)[..]);
+        src.extend_from_slice(&encoded(&AliveCheckRequest {}));
+
+        let err = codec.decode(&mut src).unwrap_err();
+        assert!(matches!(err, DoIpError::MessageTooLarge { .. }));
+        let (_, message) = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+    }
+
+    #[test]
+    fn encode() {
+        let response = AliveCheckResponse {
+            source_address: 0x045e,
+        };
+        let mut codec = DoIpCodec::new();
+        let mut dst = BytesMut::new();
+        codec.encode(&response, &mut dst).unwrap();
+        codec
+            .encode(DoIpMessage::AliveCheckResponse(response.clone()), &mut dst)
+            .unwrap();
+        let mut expected = encoded(&response);
+        expected.extend(encoded(&response));
+        assert_eq!(&dst[..], &expected[..]);
+    }
+
+    #[test]
+    fn version_echo() {
+        let mut codec =
+            DoIpCodec::new().with_version_policy(VersionPolicy::Echo(ProtocolVersion::DoIpIso));
+        let mut src = BytesMut::new();
+        write_message_versioned(
+            ProtocolVersion::Iso13400_2019,
+            &AliveCheckRequest {},
+            &mut (&mut src).writer(),
+        )
+        .unwrap();
+        codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(codec.protocol_version(), ProtocolVersion::Iso13400_2019);
+
+        let mut dst = BytesMut::new();
+        let response = AliveCheckResponse {
+            source_address: 0x045e,
+        };
+        codec.encode(&response, &mut dst).unwrap();
+        assert_eq!(&dst[..2], &[0x03, 0xfc]);
+    }
+}
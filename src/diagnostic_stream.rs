@@ -0,0 +1,273 @@
+use byteorder::{BigEndian, ByteOrder};
+use std::io::{self, ErrorKind, Read, Take, Write};
+
+use crate::io::ReadBytesExt;
+use crate::message::ProtocolVersion;
+use crate::{DoIpError, DoIpHeader, LogicalAddress, PayloadType, DOIP_HEADER_LENGTH};
+
+/// Length of the source and target addresses of a diagnostic message.
+const ADDRESSES_LENGTH: usize = 4;
+
+/// Streamed reader of a [`DiagnosticMessage`](crate::message::DiagnosticMessage)
+///
+/// The reader decodes the source and target addresses of the message, and
+/// then exposes its user data as a [`Read`] bounded to the payload length, so
+/// that a large UDS message, such as a TransferData one, can be processed
+/// without buffering it whole.
+///
+/// The user data should be read completely before the next message is read
+/// from the underlying reader.
+///
+/// Example:
+/// ```
+/// use doip_rw::{read_header, DiagnosticMessageReader, PayloadType};
+/// use std::io::{self, Cursor};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp = Cursor::new([
+///     0x02, 0xfd, 0x80, 0x01, 0x00, 0x00, 0x00, 0x07, 0x0e, 0x00, 0x00, 0x77, 0x36, 0x01, 0xaa,
+/// ]);
+/// let header = read_header(&mut tcp)?;
+/// if header.payload_type == PayloadType::DiagnosticMessage {
+///     let mut message = DiagnosticMessageReader::new(&mut tcp, &header)?;
+///     assert_eq!(message.source_address(), 0x0e00);
+///     // let mut file = File::create("block.bin")?;
+///     let mut file = vec![];
+///     io::copy(&mut message, &mut file)?;
+///     assert_eq!(file, [0x36, 0x01, 0xaa]);
+/// }
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+#[derive(Debug)]
+pub struct DiagnosticMessageReader<R> {
+    source_address: LogicalAddress,
+    target_address: LogicalAddress,
+    user_data: Take<R>,
+}
+
+impl<R: Read> DiagnosticMessageReader<R> {
+    /// Reads the addresses of the diagnostic message following `header`.
+    ///
+    /// The header must have already been read from `reader`.
+    pub fn new(mut reader: R, header: &DoIpHeader) -> Result<Self, DoIpError> {
+        if header.payload_type != PayloadType::DiagnosticMessage {
+            return Err(DoIpError::UnexpectedPayloadType {
+                value: header.payload_type.into_u16(),
+            });
+        }
+        if (header.payload_length as usize) < ADDRESSES_LENGTH {
+            return Err(DoIpError::PayloadLengthTooShort {
+                value: header.payload_length,
+                expected: ADDRESSES_LENGTH as u32,
+            });
+        }
+        let source_address = reader.read_u16::<BigEndian>()?;
+        let target_address = reader.read_u16::<BigEndian>()?;
+        let user_data_length = header.payload_length as u64 - ADDRESSES_LENGTH as u64;
+        Ok(Self {
+            source_address,
+            target_address,
+            user_data: reader.take(user_data_length),
+        })
+    }
+
+    /// Get the logical address of the sender.
+    pub fn source_address(&self) -> LogicalAddress {
+        self.source_address
+    }
+
+    /// Get the logical address of the target of the message.
+    pub fn target_address(&self) -> LogicalAddress {
+        self.target_address
+    }
+
+    /// Get the number of user data bytes not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.user_data.limit()
+    }
+
+    /// Get back the underlying reader.
+    ///
+    /// The user data not read yet is left in the reader.
+    pub fn into_inner(self) -> R {
+        self.user_data.into_inner()
+    }
+}
+
+impl<R: Read> Read for DiagnosticMessageReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.user_data.read(buf)?;
+        if len == 0 && !buf.is_empty() && self.user_data.limit() > 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(len)
+    }
+}
+
+/// Writes a diagnostic message, streaming its user data from a reader
+///
+/// The DoIP header and the addresses are written first, then
+/// `user_data_length` bytes are copied from `user_data`, without buffering
+/// them whole. This is the streamed counterpart of [`crate::write_message()`]
+/// for a [`DiagnosticMessage`](crate::message::DiagnosticMessage).
+///
+/// If `user_data` ends before `user_data_length` bytes, an
+/// [`ErrorKind::UnexpectedEof`] error is returned, and the message written
+/// so far is truncated.
+///
+/// Example:
+/// ```
+/// use doip_rw::write_diagnostic_message_from;
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp = vec![];
+/// // let file = File::open("block.bin")?;
+/// // let length = file.metadata()?.len() as u32;
+/// let file = &[0x36, 0x01, 0xaa][..];
+/// write_diagnostic_message_from(0x0e00, 0x0077, file, 3, &mut tcp)?;
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub fn write_diagnostic_message_from<R: Read, W: Write>(
+    source_address: LogicalAddress,
+    target_address: LogicalAddress,
+    user_data: R,
+    user_data_length: u32,
+    writer: &mut W,
+) -> Result<(), DoIpError> {
+    write_diagnostic_message_from_versioned(
+        ProtocolVersion::DoIpIso,
+        source_address,
+        target_address,
+        user_data,
+        user_data_length,
+        writer,
+    )
+}
+
+/// Writes a diagnostic message, streaming its user data from a reader, with a
+/// given protocol version
+///
+/// This function is [`write_diagnostic_message_from()`] with the choice of
+/// the protocol version written in the header.
+pub fn write_diagnostic_message_from_versioned<R: Read, W: Write>(
+    protocol_version: ProtocolVersion,
+    source_address: LogicalAddress,
+    target_address: LogicalAddress,
+    user_data: R,
+    user_data_length: u32,
+    writer: &mut W,
+) -> Result<(), DoIpError> {
+    let max = u32::MAX - ADDRESSES_LENGTH as u32;
+    if user_data_length > max {
+        return Err(DoIpError::MessageTooLarge {
+            value: user_data_length,
+            max,
+        });
+    }
+    let header = DoIpHeader::new_versionned(
+        protocol_version,
+        PayloadType::DiagnosticMessage,
+        user_data_length + ADDRESSES_LENGTH as u32,
+    );
+    let mut start = [0u8; DOIP_HEADER_LENGTH + ADDRESSES_LENGTH];
+    header.write(&mut &mut start[..DOIP_HEADER_LENGTH])?;
+    BigEndian::write_u16(&mut start[DOIP_HEADER_LENGTH..], source_address);
+    BigEndian::write_u16(&mut start[DOIP_HEADER_LENGTH + 2..], target_address);
+    writer.write_all(&start)?;
+
+    let copied = io::copy(&mut user_data.take(user_data_length as u64), writer)?;
+    if copied != user_data_length as u64 {
+        return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{read_header, read_message, write_message};
+
+    fn user_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn write_read_streamed() {
+        let data = user_data(70000);
+        let mut stream = vec![];
+        write_diagnostic_message_from(0x0e00, 0x0077, &data[..], 70000, &mut stream).unwrap();
+        write_message(&AliveCheckRequest {}, &mut stream).unwrap();
+
+        let expected = DiagnosticMessage {
+            source_address: 0x0e00,
+            target_address: 0x0077,
+            user_data: UdsBuffer::Borrowed(&data),
+        };
+        let mut encoded = vec![];
+        write_message(&expected, &mut encoded).unwrap();
+        assert_eq!(&stream[..encoded.len()], &encoded[..]);
+
+        let mut reader = &stream[..];
+        let header = read_header(&mut reader).unwrap();
+        let mut message = DiagnosticMessageReader::new(&mut reader, &header).unwrap();
+        assert_eq!(message.source_address(), 0x0e00);
+        assert_eq!(message.target_address(), 0x0077);
+        assert_eq!(message.remaining(), 70000);
+        let mut received = vec![0u8; 1000];
+        message.read_exact(&mut received).unwrap();
+        assert_eq!(message.remaining(), 69000);
+        message.read_to_end(&mut received).unwrap();
+        assert_eq!(received, data);
+        let _: AliveCheckRequest = read_message(message.into_inner()).unwrap();
+    }
+
+    #[test]
+    fn read_truncated() {
+        let mut stream = vec![];
+        let data = user_data(16);
+        write_diagnostic_message_from(0x0e00, 0x0077, &data[..], 16, &mut stream).unwrap();
+        stream.truncate(stream.len() - 1);
+
+        let mut reader = &stream[..];
+        let header = read_header(&mut reader).unwrap();
+        let mut message = DiagnosticMessageReader::new(&mut reader, &header).unwrap();
+        let err = io::copy(&mut message, &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_wrong_header() {
+        let mut stream = vec![];
+        write_message(&AliveCheckRequest {}, &mut stream).unwrap();
+        let mut reader = &stream[..];
+        let header = read_header(&mut reader).unwrap();
+        assert!(matches!(
+            DiagnosticMessageReader::new(&mut reader, &header),
+            Err(DoIpError::UnexpectedPayloadType { value: 0x0007 })
+        ));
+
+        let header = DoIpHeader::new(PayloadType::DiagnosticMessage, 3);
+        assert!(matches!(
+            DiagnosticMessageReader::new(&mut reader, &header),
+            Err(DoIpError::PayloadLengthTooShort { value: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn write_short_user_data() {
+        let data = user_data(16);
+        let mut stream = vec![];
+        let res = write_diagnostic_message_from_versioned(
+            ProtocolVersion::Iso13400_2019,
+            0x0e00,
+            0x0077,
+            &data[..],
+            17,
+            &mut stream,
+        );
+        assert!(matches!(res, Err(DoIpError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof));
+        assert_eq!(stream[0], 0x03);
+        assert_eq!(stream.len(), DOIP_HEADER_LENGTH + ADDRESSES_LENGTH + 16);
+    }
+}
@@ -0,0 +1,234 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::message::*;
+use crate::{read_any_message, write_message_versioned, DoIpError, DoIpMessage, Vin};
+
+/// UDP port of the DoIP entities, where identification requests are sent
+/// (UDP_DISCOVERY).
+pub const UDP_DISCOVERY_PORT: u16 = 13400;
+/// UDP port of the external testers, where vehicle announcements are sent
+/// (UDP_TEST_EQUIPMENT_REQUEST).
+pub const UDP_TEST_EQUIPMENT_PORT: u16 = 13401;
+
+/// Maximum size of a received UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 1500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Vehicle identification request sent by [`discover_vehicles_with`].
+pub enum DiscoveryRequest {
+    /// All the DoIP entities are requested to answer.
+    All,
+    /// Only the DoIP entities with this EID are requested to answer.
+    Eid(Eid),
+    /// Only the DoIP entities with this VIN are requested to answer.
+    Vin(Vin),
+}
+
+/// A discovered DoIP entity : the address it answered from, and its
+/// identification.
+pub type DiscoveredVehicle = (SocketAddr, VehicleIdentificationResponse);
+
+/// Discovers the DoIP entities
+///
+/// A [`VehicleIdentificationRequest`] is sent from `bind_addr` to
+/// `broadcast_addr`, and the responses are collected until `timeout` expires.
+/// Repeated responses from the same entity are only reported once.
+///
+/// Example:
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let vehicles = doip_rw::discover_vehicles(
+///     "0.0.0.0:0",
+///     "255.255.255.255:13400",
+///     Duration::from_millis(500),
+/// )?;
+/// for (addr, vehicle) in vehicles {
+///     println!("{} : {:04x}", addr, vehicle.logical_address);
+/// }
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub fn discover_vehicles<A: ToSocketAddrs, B: ToSocketAddrs>(
+    bind_addr: A,
+    broadcast_addr: B,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredVehicle>, DoIpError> {
+    discover_vehicles_with(bind_addr, broadcast_addr, DiscoveryRequest::All, timeout)
+}
+
+/// Discovers the DoIP entities matching `request`
+///
+/// Same as [`discover_vehicles`], but with the choice of the identification
+/// request, to only discover the entities of a given EID or VIN.
+pub fn discover_vehicles_with<A: ToSocketAddrs, B: ToSocketAddrs>(
+    bind_addr: A,
+    broadcast_addr: B,
+    request: DiscoveryRequest,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredVehicle>, DoIpError> {
+    let udp = UdpSocket::bind(bind_addr)?;
+    udp.set_broadcast(true)?;
+    // The default protocol version, as the entities one is not known yet.
+    let version = ProtocolVersion::VehicleIdentificationRequest;
+    let mut buf = vec![];
+    match request {
+        DiscoveryRequest::All => {
+            write_message_versioned(version, &VehicleIdentificationRequest {}, &mut buf)?
+        }
+        DiscoveryRequest::Eid(eid) => write_message_versioned(
+            version,
+            &VehicleIdentificationRequestWithEid { eid },
+            &mut buf,
+        )?,
+        DiscoveryRequest::Vin(vin) => write_message_versioned(
+            version,
+            &VehicleIdentificationRequestWithVin { vin },
+            &mut buf,
+        )?,
+    }
+    udp.send_to(&buf, broadcast_addr)?;
+    collect_announcements(&udp, timeout)
+}
+
+/// Listens for vehicle announcements
+///
+/// The vehicle announcements received on `bind_addr`, usually on port
+/// [`UDP_TEST_EQUIPMENT_PORT`] or [`UDP_DISCOVERY_PORT`], are collected until
+/// `timeout` expires. Repeated announcements from the same entity are only
+/// reported once.
+pub fn listen_announcements<A: ToSocketAddrs>(
+    bind_addr: A,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredVehicle>, DoIpError> {
+    let udp = UdpSocket::bind(bind_addr)?;
+    collect_announcements(&udp, timeout)
+}
+
+/// Collects the vehicle announcements and identification responses
+///
+/// The datagrams received on `udp` are collected until `timeout` expires.
+/// Datagrams which are not a [`VehicleIdentificationResponse`] are ignored,
+/// and repeated announcements from the same entity, ie. from the same address
+/// and with the same EID, are only reported once, with the latest one.
+pub fn collect_announcements(
+    udp: &UdpSocket,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredVehicle>, DoIpError> {
+    let deadline = Instant::now() + timeout;
+    let mut vehicles: Vec<DiscoveredVehicle> = vec![];
+    let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(vehicles);
+        }
+        udp.set_read_timeout(Some(remaining))?;
+        let (len, addr) = match udp.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(vehicles);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Ok(DoIpMessage::VehicleIdentificationResponse(vehicle)) =
+            read_any_message(&mut &buf[..len])
+        {
+            match vehicles
+                .iter_mut()
+                .find(|(known_addr, known)| *known_addr == addr && known.eid == vehicle.eid)
+            {
+                Some(known) => known.1 = vehicle,
+                None => vehicles.push((addr, vehicle)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::write_message;
+    use std::thread;
+
+    const EID: Eid = Eid([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]);
+
+    fn vehicle(logical_address: u16) -> VehicleIdentificationResponse {
+        VehicleIdentificationResponse {
+            vin: Vin(*b"VF1YYYYYZTT000001"),
+            logical_address,
+            eid: EID,
+            gid: None,
+            further_action: FurtherActionRequired::NoFurtherActionRequired,
+            vin_gid_sync_status: Some(VinGidSyncStatus::Synchronized),
+        }
+    }
+
+    /// Answers one identification request, repeating the response 3 times as
+    /// the vehicle announcements.
+    fn responder() -> SocketAddr {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = udp.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; MAX_DATAGRAM_SIZE];
+            let (len, tester) = udp.recv_from(&mut buf).unwrap();
+            let answer = match read_any_message(&mut &buf[..len]).unwrap() {
+                DoIpMessage::VehicleIdentificationRequest(_) => true,
+                DoIpMessage::VehicleIdentificationRequestWithEid(r) => r.eid == EID,
+                DoIpMessage::VehicleIdentificationRequestWithVin(r) => r.vin == vehicle(0).vin,
+                _ => false,
+            };
+            if answer {
+                let mut response = vec![];
+                write_message(&vehicle(0x0077), &mut response).unwrap();
+                for _ in 0..3 {
+                    udp.send_to(&response, tester).unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn discover() {
+        let timeout = Duration::from_millis(200);
+        let entity = responder();
+        let vehicles = discover_vehicles("127.0.0.1:0", entity, timeout).unwrap();
+        assert_eq!(vehicles, vec![(entity, vehicle(0x0077))]);
+
+        let entity = responder();
+        let request = DiscoveryRequest::Eid(EID);
+        let vehicles = discover_vehicles_with("127.0.0.1:0", entity, request, timeout).unwrap();
+        assert_eq!(vehicles, vec![(entity, vehicle(0x0077))]);
+
+        let entity = responder();
+        let request = DiscoveryRequest::Vin(Vin(*b"VF1YYYYYZTT000002"));
+        let vehicles = discover_vehicles_with("127.0.0.1:0", entity, request, timeout).unwrap();
+        assert!(vehicles.is_empty());
+    }
+
+    #[test]
+    fn announcements() {
+        let tester = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tester_addr = tester.local_addr().unwrap();
+        let entity = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let entity_addr = entity.local_addr().unwrap();
+        let mut announce = vec![];
+        write_message(&vehicle(0x0077), &mut announce).unwrap();
+        let mut other = vec![];
+        write_message(&AliveCheckRequest {}, &mut other).unwrap();
+        let mut updated_vehicle = vehicle(0x0077);
+        updated_vehicle.further_action =
+            FurtherActionRequired::RoutingActivationRequiredToInitiateCentralSecurity;
+        updated_vehicle.vin_gid_sync_status = Some(VinGidSyncStatus::Incomplete);
+        let mut updated = vec![];
+        write_message(&updated_vehicle, &mut updated).unwrap();
+        for datagram in [&announce, &other, &announce, &updated] {
+            entity.send_to(datagram, tester_addr).unwrap();
+        }
+
+        let vehicles = collect_announcements(&tester, Duration::from_millis(100)).unwrap();
+        assert_eq!(vehicles, vec![(entity_addr, updated_vehicle)]);
+    }
+}
@@ -1,5 +1,11 @@
+use alloc::vec::Vec;
 use thiserror::Error;
 
+use crate::DoIpHeader;
+
+use crate::message::{DiagnosticMessageNegativeAckCode, RoutingActivationResponseCode};
+use crate::message::{GenericDoIpHeaderNegativeAcknowledge, NegativeAckCode};
+
 #[derive(Error, Debug)]
 /// DoIp generic error return
 ///
@@ -25,16 +31,145 @@ pub enum DoIpError {
     /// is unknown.
     #[error("Unknown routing activation response code value: {0}")]
     UnknownRoutingActivationResponseCode(u8),
+    /// The DoIP protocol version is unknown.
+    #[error("Unknown protocol version: {0:#04x}")]
+    UnknownProtocolVersion(u8),
+    /// The input slice does not hold a complete DoIP message. The expected
+    /// length is the header length while the header is incomplete, and the
+    /// whole message length afterwards.
+    #[error("Incomplete message: {value} bytes available, expected: {expected}")]
+    IncompleteMessage {
+        /// Length of the input slice.
+        value: usize,
+        /// Minimum length of a complete message.
+        expected: usize,
+    },
     /// The playload type is not valid.
     #[error("Unexpected payload type found: {value:?}")]
     UnexpectedPayloadType {
         /// Payload type received and invalid.
         value: u16,
     },
-    /// The provided buffer for reading the message is too small.
-    #[error("Buffer to small")]
-    BufferTooSmall,
+    /// A message of another payload type than the expected one was received,
+    /// and read according to
+    /// [`UnexpectedPayloadPolicy::Return`](crate::UnexpectedPayloadPolicy::Return).
+    #[error("Unexpected message of payload type {:#06x}", header.payload_type.into_u16())]
+    UnexpectedMessage {
+        /// Header of the received message.
+        header: DoIpHeader,
+        /// Raw payload of the received message.
+        payload: Vec<u8>,
+    },
+    /// The DoIP header protocol version is unsupported, or its inverse doesn't
+    /// match.
+    #[error("Incorrect pattern format: protocol version {protocol_version:#04x}, inverse {inverse_protocol_version:#04x}")]
+    IncorrectPatternFormat {
+        /// Protocol version in the received DoIP header.
+        protocol_version: u8,
+        /// Inverse protocol version in the received DoIP header.
+        inverse_protocol_version: u8,
+    },
+    /// The payload type is not supported.
+    #[error("Unknown payload type: {0:#06x}")]
+    UnknownPayloadType(u16),
+    /// The payload length exceeds the maximum accepted length.
+    #[error("Message too large: payload length {value}, maximum: {max}")]
+    MessageTooLarge {
+        /// Payload length in the received DoIP header.
+        value: u32,
+        /// Maximum accepted payload length.
+        max: u32,
+    },
+    /// The payload length is incompatible with the payload type.
+    #[error("Invalid payload length {value} for payload type {payload_type:#06x}")]
+    InvalidPayloadLength {
+        /// Payload length in the received DoIP header.
+        value: u32,
+        /// Payload type in the received DoIP header.
+        payload_type: u16,
+    },
+    /// The VIN doesn't have 17 characters.
+    #[error("Invalid VIN length: {0}, expected: 17")]
+    InvalidVinLength(usize),
+    /// The VIN holds a character not allowed by ISO 3779.
+    #[error("Invalid VIN character {character:?} at position {position}")]
+    InvalidVinCharacter {
+        /// Position of the character in the VIN.
+        position: usize,
+        /// Invalid character.
+        character: char,
+    },
+    /// The VIN check digit doesn't match the other characters.
+    #[error("Invalid VIN check digit: {value:?}, expected: {expected:?}")]
+    InvalidVinCheckDigit {
+        /// Check digit in the VIN.
+        value: char,
+        /// Check digit computed from the other characters.
+        expected: char,
+    },
+    /// The peer rejected a DoIP header with a
+    /// [`GenericDoIpHeaderNegativeAcknowledge`].
+    #[error("DoIP header negatively acknowledged: {0:?}")]
+    HeaderNegativeAck(NegativeAckCode),
+    /// The routing activation was denied by the DoIP entity.
+    #[error("Routing activation denied: {0:?}")]
+    RoutingActivationDenied(RoutingActivationResponseCode),
+    /// The diagnostic message was negatively acknowledged by the DoIP entity.
+    #[error("Diagnostic message negatively acknowledged: {0:?}")]
+    DiagnosticMessageNegativeAck(DiagnosticMessageNegativeAckCode),
+    /// The expected DoIP message was not received in time.
+    #[error("Timeout while waiting for a DoIP message")]
+    Timeout,
+    /// The provided buffer is too small to encode the message.
+    #[error("Buffer too small: {value} bytes available, expected: {expected}")]
+    BufferTooSmall {
+        /// Length of the provided buffer.
+        value: usize,
+        /// Length required by the message.
+        expected: usize,
+    },
+    /// The message can't be read into a borrowed
+    /// [`UdsBuffer`](crate::message::UdsBuffer), which can't receive any data.
+    #[error("Can't read {0} bytes of user data into a borrowed buffer")]
+    BorrowedBuffer(usize),
     /// An input/output error occurred while using a reader or a writer.
     #[error(transparent)]
-    Io(#[from] std::io::Error),
+    Io(#[from] crate::io::Error),
+}
+
+impl DoIpError {
+    /// Get the generic header negative acknowledge answering this error.
+    ///
+    /// A DoIP entity receiving an incorrect DoIP header should reply with a
+    /// [`GenericDoIpHeaderNegativeAcknowledge`]. This function provides that
+    /// reply for errors returned by
+    /// [`HeaderValidator::validate()`](crate::HeaderValidator::validate), and
+    /// for payloads which could not be decoded because of their length.
+    ///
+    /// Returns `None` if the error is not related to the DoIP header.
+    pub fn header_nack(&self) -> Option<GenericDoIpHeaderNegativeAcknowledge> {
+        use DoIpError::*;
+        let nack_code = match self {
+            IncorrectPatternFormat { .. } => NegativeAckCode::IncorrectPatternFormat,
+            UnknownPayloadType(_) => NegativeAckCode::UnknownPayloadType,
+            MessageTooLarge { .. } => NegativeAckCode::MessageTooLarge,
+            InvalidPayloadLength { .. } | PayloadLengthTooShort { .. } => {
+                NegativeAckCode::InvalidPayloadLength
+            }
+            _ => return None,
+        };
+        Some(GenericDoIpHeaderNegativeAcknowledge { nack_code })
+    }
+
+    /// Tells if the DoIP entity denied the routing activation because it only
+    /// accepts TLS connections, in which case the tester should connect again
+    /// on the TLS port (3496).
+    pub fn requires_tls(&self) -> bool {
+        matches!(
+            self,
+            DoIpError::RoutingActivationDenied(
+                RoutingActivationResponseCode::RoutingActivationDeniedEncryptedConnectionViaTLSRequired
+            )
+        )
+    }
 }
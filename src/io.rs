@@ -0,0 +1,474 @@
+//! IO traits used by the DoIP encoding and decoding
+//!
+//! With the `std` feature, these are the [`std::io`] traits and error, so
+//! that any standard reader or writer, such as a `TcpStream`, can be used.
+//!
+//! Without the `std` feature, a minimal equivalent is provided, implemented
+//! for byte slices and `Vec<u8>`. It can be implemented for any other
+//! transport, such as an embedded network stack.
+use alloc::vec::Vec;
+use byteorder::ByteOrder;
+
+use crate::PayloadType;
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Write};
+
+#[cfg(not(feature = "std"))]
+pub use no_std::{Error, ErrorKind, Read, Write};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// Kind of IO error.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// The reader ended before the expected number of bytes.
+        UnexpectedEof,
+        /// The writer could not accept all the bytes.
+        WriteZero,
+        /// Any other transport error.
+        Other,
+    }
+
+    /// IO error, as [`std::io::Error`] without the `std` feature.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Error {
+        kind: ErrorKind,
+    }
+
+    impl Error {
+        /// Get the kind of this error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.kind {
+                ErrorKind::UnexpectedEof => f.write_str("unexpected end of file"),
+                ErrorKind::WriteZero => f.write_str("failed to write whole buffer"),
+                ErrorKind::Other => f.write_str("other error"),
+            }
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// Source of bytes, as [`std::io::Read`] without the `std` feature.
+    pub trait Read {
+        /// Pulls some bytes into `buf`, and returns how many bytes were read.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+        /// Reads exactly enough bytes to fill `buf`.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Error> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => return Err(ErrorKind::UnexpectedEof.into()),
+                    n => buf = &mut buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Sink of bytes, as [`std::io::Write`] without the `std` feature.
+    pub trait Write {
+        /// Writes some bytes from `buf`, and returns how many bytes were
+        /// written.
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+
+        /// Flushes the buffered bytes, if any.
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        /// Writes the whole `buf`.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(ErrorKind::WriteZero.into()),
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let len = buf.len().min(self.len());
+            let (head, tail) = self.split_at(len);
+            buf[..len].copy_from_slice(head);
+            *self = tail;
+            Ok(len)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            (**self).read(buf)
+        }
+    }
+
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            let len = buf.len().min(self.len());
+            let (head, tail) = core::mem::take(self).split_at_mut(len);
+            head.copy_from_slice(&buf[..len]);
+            *self = tail;
+            Ok(len)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            (**self).flush()
+        }
+    }
+}
+
+/// Decoding of integers, for any [`Read`].
+pub(crate) trait ReadBytesExt: Read {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u16<B: ByteOrder>(&mut self) -> Result<u16, Error> {
+        let mut buf = [0u8; 2];
+        self.read_exact(&mut buf)?;
+        Ok(B::read_u16(&buf))
+    }
+
+    fn read_u32<B: ByteOrder>(&mut self) -> Result<u32, Error> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(B::read_u32(&buf))
+    }
+}
+
+impl<R: Read + ?Sized> ReadBytesExt for R {}
+
+/// Encoding of integers, for any [`Write`].
+pub(crate) trait WriteBytesExt: Write {
+    fn write_u8(&mut self, value: u8) -> Result<(), Error> {
+        self.write_all(&[value])
+    }
+
+    fn write_u16<B: ByteOrder>(&mut self, value: u16) -> Result<(), Error> {
+        let mut buf = [0u8; 2];
+        B::write_u16(&mut buf, value);
+        self.write_all(&buf)
+    }
+
+    fn write_u32<B: ByteOrder>(&mut self, value: u32) -> Result<(), Error> {
+        let mut buf = [0u8; 4];
+        B::write_u32(&mut buf, value);
+        self.write_all(&buf)
+    }
+}
+
+impl<W: Write + ?Sized> WriteBytesExt for W {}
+
+/// Size of the buffer gathering the small writes of a message.
+const COALESCE_SIZE: usize = 64;
+
+/// Writer gathering the small writes of a DoIP message
+///
+/// The header and the fixed fields of a payload are encoded with many small
+/// writes, each of which would be a syscall, and possibly a TCP segment, on an
+/// unbuffered socket. They are gathered in a stack buffer instead, and written
+/// along with the first large write, such as the user data of a diagnostic
+/// message, with a single vectored write. The remaining bytes are written by
+/// [`CoalescingWriter::finish()`].
+pub(crate) struct CoalescingWriter<'a, W: Write + ?Sized> {
+    inner: &'a mut W,
+    buf: [u8; COALESCE_SIZE],
+    len: usize,
+}
+
+impl<'a, W: Write + ?Sized> CoalescingWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self {
+            inner,
+            buf: [0; COALESCE_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Writes the gathered bytes to the inner writer.
+    pub(crate) fn finish(mut self) -> Result<(), Error> {
+        self.write_buffered(&[])
+    }
+
+    fn write_buffered(&mut self, data: &[u8]) -> Result<(), Error> {
+        let buffered = self.len;
+        self.len = 0;
+        write_all_vectored(self.inner, &self.buf[..buffered], data)
+    }
+}
+
+impl<W: Write + ?Sized> Write for CoalescingWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        match self.buf.get_mut(self.len..self.len + data.len()) {
+            Some(free) => {
+                free.copy_from_slice(data);
+                self.len += data.len();
+            }
+            None => self.write_buffered(data)?,
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.write_buffered(&[])?;
+        self.inner.flush()
+    }
+}
+
+/// Writes `first` and then `second`, with as few writes as possible.
+#[cfg(feature = "std")]
+fn write_all_vectored<W: Write + ?Sized>(
+    writer: &mut W,
+    first: &[u8],
+    second: &[u8],
+) -> Result<(), Error> {
+    use std::io::IoSlice;
+    let mut slices = [IoSlice::new(first), IoSlice::new(second)];
+    let mut bufs = &mut slices[..];
+    IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        match writer.write_vectored(bufs) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Writes `first` and then `second`, with as few writes as possible.
+#[cfg(not(feature = "std"))]
+fn write_all_vectored<W: Write + ?Sized>(
+    writer: &mut W,
+    first: &[u8],
+    second: &[u8],
+) -> Result<(), Error> {
+    writer.write_all(first)?;
+    writer.write_all(second)
+}
+
+/// Size of the buffer receiving the start of a payload.
+const PREFETCH_SIZE: usize = 64;
+
+/// Reader of a DoIP payload, receiving its first bytes with a single read
+///
+/// A payload is decoded field by field, each of which would be a syscall on
+/// an unbuffered socket. The first bytes of the payload, ie. the whole payload
+/// but for the user data of the diagnostic messages, are read at once into a
+/// stack buffer instead, and decoded from there.
+///
+/// Nothing is read in advance if the payload length is invalid for the payload
+/// type, so that the decoding fails without waiting for bytes which may never
+/// come.
+pub(crate) struct PayloadReader<'a, R: Read + ?Sized> {
+    inner: &'a mut R,
+    buf: [u8; PREFETCH_SIZE],
+    pos: usize,
+    len: usize,
+}
+
+impl<'a, R: Read + ?Sized> PayloadReader<'a, R> {
+    pub(crate) fn new(
+        inner: &'a mut R,
+        payload_type: PayloadType,
+        payload_length: usize,
+    ) -> Result<Self, Error> {
+        let mut buf = [0; PREFETCH_SIZE];
+        let len = if payload_type.is_valid_length(payload_length) {
+            payload_length.min(PREFETCH_SIZE)
+        } else {
+            0
+        };
+        inner.read_exact(&mut buf[..len])?;
+        Ok(Self {
+            inner,
+            buf,
+            pos: 0,
+            len,
+        })
+    }
+}
+
+impl<R: Read + ?Sized> Read for PayloadReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.pos == self.len {
+            return self.inner.read(buf);
+        }
+        let n = buf.len().min(self.len - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Reads and discards exactly `len` bytes.
+pub(crate) fn skip<R: Read + ?Sized>(reader: &mut R, mut len: usize) -> Result<(), Error> {
+    let mut buf = [0u8; 256];
+    while len > 0 {
+        let chunk = len.min(buf.len());
+        reader.read_exact(&mut buf[..chunk])?;
+        len -= chunk;
+    }
+    Ok(())
+}
+
+/// Reads exactly `len` bytes into `buf`.
+///
+/// The buffer grows as data is received, so that a bogus length doesn't
+/// trigger a huge allocation.
+pub(crate) fn read_to_vec<R: Read + ?Sized>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    len: usize,
+) -> Result<(), Error> {
+    const CHUNK_SIZE: usize = 4096;
+    buf.clear();
+    while buf.len() < len {
+        let start = buf.len();
+        buf.resize(len.min(start + CHUNK_SIZE), 0);
+        if let Err(e) = reader.read_exact(&mut buf[start..]) {
+            buf.truncate(start);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{read_message, write_message, Payload};
+    use core::fmt::Debug;
+    use std::io::IoSlice;
+
+    /// Writer counting the calls to `write()` and `write_vectored()`.
+    #[derive(Default)]
+    struct CountingWriter {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.writes += 1;
+            self.data.write(buf)
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize, Error> {
+            self.writes += 1;
+            self.data.write_vectored(bufs)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn writes<P: Payload>(payload: &P) -> usize {
+        let mut direct = Vec::new();
+        let header = crate::DoIpHeader::new(P::payload_type(), payload.length() as u32);
+        header.write(&mut direct).unwrap();
+        payload.write(&mut direct).unwrap();
+
+        let mut writer = CountingWriter::default();
+        write_message(payload, &mut writer).unwrap();
+        assert_eq!(writer.data, direct);
+        writer.writes
+    }
+
+    #[test]
+    fn single_write_per_message() {
+        assert_eq!(writes(&AliveCheckRequest {}), 1);
+        assert_eq!(
+            writes(&RoutingActivationRequest::builder(0x0e00).build()),
+            1
+        );
+        let vin = "VF1YYYYYZTT000001".parse().unwrap();
+        let announcement = VehicleIdentificationResponse::builder(vin, 0x0077, Eid([1; 6]))
+            .gid([2; 6])
+            .build();
+        assert_eq!(writes(&announcement), 1);
+        let request = DiagnosticMessage::new(0x0e00, 0x0077, vec![0x22, 0xf1, 0x90]);
+        assert_eq!(writes(&request), 1);
+        let transfer = DiagnosticMessage::new(0x0e00, 0x0077, vec![0x36; 4096]);
+        assert_eq!(writes(&transfer), 1);
+    }
+
+    /// Reader counting the calls to `read()`.
+    struct CountingReader<'a> {
+        data: &'a [u8],
+        reads: usize,
+    }
+
+    impl Read for CountingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            self.reads += 1;
+            self.data.read(buf)
+        }
+    }
+
+    fn reads<P: Payload + PartialEq + Debug>(payload: &P) -> usize {
+        let mut encoded = Vec::new();
+        write_message(payload, &mut encoded).unwrap();
+
+        let mut reader = CountingReader {
+            data: &encoded,
+            reads: 0,
+        };
+        let decoded: P = read_message(&mut reader).unwrap();
+        assert_eq!(&decoded, payload);
+        reader.reads
+    }
+
+    #[test]
+    fn single_read_per_payload() {
+        assert_eq!(reads(&AliveCheckRequest {}), 1);
+        assert_eq!(reads(&RoutingActivationRequest::builder(0x0e00).build()), 2);
+        let vin = "VF1YYYYYZTT000001".parse().unwrap();
+        let announcement = VehicleIdentificationResponse::builder(vin, 0x0077, Eid([1; 6]))
+            .gid([2; 6])
+            .build();
+        assert_eq!(reads(&announcement), 2);
+        let request = DiagnosticMessage::new(0x0e00, 0x0077, vec![0x22, 0xf1, 0x90]);
+        assert_eq!(reads(&request), 2);
+        // The user data not prefetched are read directly in their buffer.
+        let transfer = DiagnosticMessage::new(0x0e00, 0x0077, vec![0x36; 4096]);
+        assert_eq!(reads(&transfer), 3);
+    }
+}
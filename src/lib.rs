@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 //! DoIP protocol encoding and decoding library
 //!
@@ -18,38 +19,137 @@
 //! - call [`read_payload()`] on the correct type.
 //! - see documentation of [`read_message()`].
 //!
+//! If the next message type is not known beforehand, as is usually the case
+//! for a DoIP entity, [`read_any_message()`] decodes any message into a
+//! [`DoIpMessage`].
+//!
+//! When the received header matters, for example to answer with the protocol
+//! version used by the peer, [`read_framed()`] returns the payload along with
+//! its header in a [`Framed`], written back with [`write_framed()`].
+//!
 //! A typical emission sequence using the library would be :
 //! - build a message struct which implements [`Payload`].
 //! - send it with [`write_message()`].
 //! - see documentation of [`write_message()`].
+//!
+//! To encode into a pre-allocated frame rather than a writer,
+//! [`encode_message_into()`] encodes the whole message into a byte slice, and
+//! the fixed payload lengths are provided by constants such as
+//! [`message::RoutingActivationResponse::LENGTH`].
+//!
+//! When the bytes are received in chunks rather than from a blocking reader,
+//! the [`DoIpStreamParser`] buffers them and yields the complete messages.
+//! On an unbuffered reader receiving many messages, such as a `TcpStream`,
+//! the [`DoIpReader`] receives them with large reads into an internal buffer,
+//! and decodes them from there.
+//!
+//! For an external tester, the [`DoIpClient`] takes care of the whole
+//! sequence of a diagnostic session over a TCP connection. For a DoIP
+//! entity, the [`DoIpServer`] handles the connections of testers, and
+//! delegates the UDS requests to a [`DiagnosticHandler`]. Before connecting,
+//! the DoIP entities can be found with [`discover_vehicles()`], and they
+//! announce themselves and answer with a [`VehicleAnnouncer`].
+//!
+//! With the `tokio` feature, the `DoIpCodec` provides the framing of DoIP
+//! messages for asynchronous streams, and the `AsyncDoIpClient` multiplexes
+//! concurrent UDS requests over a single connection.
+//!
+//! With the `tls` feature, the `TlsStream` secures the TCP connections of
+//! both the [`DoIpClient`] and the [`DoIpServer`] with rustls, on the
+//! `TLS_PORT` of ISO 13400-2:2019.
+//!
+//! With the `serde` feature, the [`message`] types, the [`DoIpMessage`], the
+//! [`DoIpHeader`] and the [`PayloadType`] implement serde `Serialize` and
+//! `Deserialize`, for example to log the DoIP traffic as JSON. The VIN is
+//! rendered as a string, and the EID and GID as hexadecimal strings.
+//!
+//! The `std` feature is enabled by default. Without it, the crate is
+//! `no_std`, and only requires an allocator : the messages are encoded and
+//! decoded with the [`io`] module traits, implemented for byte slices and
+//! `Vec<u8>`, and [`parse_message()`] decodes without any allocation. The
+//! networking parts, such as the [`DoIpClient`] or the [`DoIpServer`], are
+//! only available with `std`.
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod announcer;
+#[cfg(feature = "tokio")]
+mod async_client;
+#[cfg(feature = "std")]
+mod client;
+#[cfg(feature = "tokio")]
+mod codec;
+#[cfg(feature = "std")]
+mod diagnostic_stream;
+#[cfg(feature = "std")]
+mod discovery;
 mod error;
+pub mod io;
+mod parser;
 mod proto;
-mod serde;
-use std::io::{Read, Write};
+mod reader;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "std")]
+mod server;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "tls")]
+mod tls;
+mod wire;
+use io::{Read, Write};
+use proto::header::ProtocolVersion;
 
+#[cfg(feature = "std")]
+pub use announcer::{AnnouncerConfig, VehicleAnnouncer};
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncDoIpClient;
+#[cfg(feature = "std")]
+pub use client::{ClientTimeouts, DoIpClient};
+#[cfg(feature = "tokio")]
+pub use codec::DoIpCodec;
+#[cfg(feature = "std")]
+pub use diagnostic_stream::{
+    write_diagnostic_message_from, write_diagnostic_message_from_versioned, DiagnosticMessageReader,
+};
+#[cfg(feature = "std")]
+pub use discovery::{
+    collect_announcements, discover_vehicles, discover_vehicles_with, listen_announcements,
+    DiscoveredVehicle, DiscoveryRequest, UDP_DISCOVERY_PORT, UDP_TEST_EQUIPMENT_PORT,
+};
 pub use error::DoIpError;
-pub use proto::header::{DoIpHeader, DOIP_HEADER_LENGTH};
+pub use parser::{DoIpStreamParser, Frame};
+pub use proto::doip_message::DoIpMessage;
+pub use proto::header::{
+    DoIpHeader, Framed, HeaderValidator, UnexpectedPayloadPolicy, VersionPolicy, DOIP_HEADER_LENGTH,
+};
 pub use proto::payload::{BorrowedPayload, Payload, PayloadType};
+pub use proto::vehicleident::Vin;
+pub use reader::DoIpReader;
+#[cfg(feature = "std")]
+pub use server::{DiagnosticHandler, DiagnosticResponse, DoIpServer, ServerConfig};
+#[cfg(feature = "std")]
+pub use stream::DoIpStream;
+#[cfg(feature = "tls")]
+pub use tls::{TlsStream, TLS_PORT};
 
 /// A DoIP logical address, both for a tester or a tested entity
 pub type LogicalAddress = u16;
-/// A Vehicle Identifier Number
-pub type Vin = [u8; 17];
 
 /// Reads a DoIP header and attemps to read a DoIp payload
 ///
 /// This function is only usable if it is known beforehand which message is
 /// coming next, in which case it returns that DoIp payload.  If used with the
-/// wrong payload type, it will return a PayloadLengthTooShort error, and stops
-/// reading after the header.
+/// wrong payload type, it will return an UnexpectedPayloadType error, and stops
+/// reading after the header. See [`read_message_with()`] to consume the
+/// payload of such a message instead.
 ///
 /// A sounder use would be :
 /// ```
 /// use doip_rw::{read_header, read_payload, PayloadType, Payload, message::AliveCheckRequest, };
-/// use std::io::Cursor;
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00];
 /// let header = read_header(&mut tcp).unwrap();
 /// match header.payload_type {
 ///   PayloadType::AliveCheckRequest => {
@@ -59,13 +159,118 @@ pub type Vin = [u8; 17];
 /// }
 /// ```
 pub fn read_message<R: Read, P: Payload>(reader: &mut R) -> Result<P, DoIpError> {
+    read_message_with(reader, UnexpectedPayloadPolicy::Leave)
+}
+
+/// Reads a DoIP header and attemps to read a DoIp payload, handling a message
+/// of another payload type according to `policy`
+///
+/// This function is [`read_message()`], but for a message of another payload
+/// type, the payload can be consumed so that the reader stays synchronized on
+/// the next DoIP header. A tester waiting for a response can then skip a
+/// stray message, or handle it, and go on reading.
+///
+/// Example:
+/// ```
+/// use doip_rw::message::{AliveCheckResponse, DiagnosticMessage};
+/// use doip_rw::{read_message_with, DoIpError, PayloadType, UnexpectedPayloadPolicy};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp: &[u8] = &[
+///     0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, // Alive check request
+///     0x02, 0xfd, 0x80, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x77, 0x0e, 0x00, 0x50, 0x01,
+/// ];
+/// let response: DiagnosticMessage = loop {
+///     match read_message_with(&mut tcp, UnexpectedPayloadPolicy::Return) {
+///         Err(DoIpError::UnexpectedMessage { header, .. }) => {
+///             // Answer the alive check request, and wait for the response again.
+///             assert_eq!(header.payload_type, PayloadType::AliveCheckRequest);
+///         }
+///         r => break r?,
+///     }
+/// };
+/// assert_eq!(response.user_data.get_ref(), &[0x50, 0x01]);
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub fn read_message_with<R: Read, P: Payload>(
+    reader: &mut R,
+    policy: UnexpectedPayloadPolicy,
+) -> Result<P, DoIpError> {
     let header = read_header(reader)?;
     if P::payload_type() == header.payload_type {
         read_payload(reader, header.payload_length as usize)
     } else {
-        Err(DoIpError::UnexpectedPayloadType {
+        Err(unexpected_payload(reader, header, policy))
+    }
+}
+
+/// Reads a DoIP message, keeping its header
+///
+/// This function is [`read_message()`], but the received [`DoIpHeader`] is
+/// returned along with the payload, so that the protocol version used by the
+/// peer is known, for example to answer with the same one.
+///
+/// Example:
+/// ```
+/// use doip_rw::message::{AliveCheckRequest, AliveCheckResponse};
+/// use doip_rw::{read_framed, write_framed, Framed};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp: &[u8] = &[0x03, 0xfc, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00];
+/// let request: Framed<AliveCheckRequest> = read_framed(&mut tcp)?;
+/// assert_eq!(request.header.protocol_version, 0x03);
+///
+/// let mut tcp = vec![];
+/// let response = request.reply(AliveCheckResponse { source_address: 0x0e00 });
+/// write_framed(&response, &mut tcp)?;
+/// assert_eq!(tcp[..2], [0x03, 0xfc]);
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub fn read_framed<R: Read, P: Payload>(reader: &mut R) -> Result<Framed<P>, DoIpError> {
+    read_framed_with(reader, UnexpectedPayloadPolicy::Leave)
+}
+
+/// Reads a DoIP message, keeping its header, and handling a message of
+/// another payload type according to `policy`
+///
+/// This function is [`read_framed()`] with the handling of
+/// [`read_message_with()`].
+pub fn read_framed_with<R: Read, P: Payload>(
+    reader: &mut R,
+    policy: UnexpectedPayloadPolicy,
+) -> Result<Framed<P>, DoIpError> {
+    let header = read_header(reader)?;
+    if P::payload_type() == header.payload_type {
+        let payload = read_payload(reader, header.payload_length as usize)?;
+        Ok(Framed { header, payload })
+    } else {
+        Err(unexpected_payload(reader, header, policy))
+    }
+}
+
+/// Handles the payload of a message of an unexpected payload type.
+fn unexpected_payload<R: Read>(
+    reader: &mut R,
+    header: DoIpHeader,
+    policy: UnexpectedPayloadPolicy,
+) -> DoIpError {
+    let length = header.payload_length as usize;
+    let consumed = match policy {
+        UnexpectedPayloadPolicy::Leave => Ok(()),
+        UnexpectedPayloadPolicy::Discard => io::skip(reader, length),
+        UnexpectedPayloadPolicy::Return => {
+            let mut payload = alloc::vec::Vec::new();
+            match io::read_to_vec(reader, &mut payload, length) {
+                Ok(()) => return DoIpError::UnexpectedMessage { header, payload },
+                Err(e) => Err(e),
+            }
+        }
+    };
+    match consumed {
+        Ok(()) => DoIpError::UnexpectedPayloadType {
             value: header.payload_type.into_u16(),
-        })
+        },
+        Err(e) => e.into(),
     }
 }
 
@@ -83,18 +288,87 @@ pub fn read_message<R: Read, P: Payload>(reader: &mut R) -> Result<P, DoIpError>
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
 /// let mut tcp = vec![];
-/// let routing_activation = RoutingActivationRequest {
-///     source_address: 0x00ed,
-///     activation_type: ActivationType::Default,
-///     reserved: [0; 4],
-///     reserved_oem: Some([0; 4]),
-/// };
+/// let routing_activation = RoutingActivationRequest::builder(0x00ed)
+///     .activation_type(ActivationType::Default)
+///     .oem([0; 4])
+///     .build();
 /// write_message(&routing_activation, &mut tcp).unwrap();
 /// ```
 pub fn write_message<W: Write, P: Payload>(payload: &P, writer: &mut W) -> Result<(), DoIpError> {
-    let header = DoIpHeader::new(P::payload_type(), payload.length() as u32);
-    header.write(writer)?;
-    payload.write(writer)
+    write_message_versioned(ProtocolVersion::DoIpIso, payload, writer)
+}
+
+/// Writes a DoIP header and the DoIp payload, with a given protocol version
+///
+/// This function is the same as [`write_message()`], but the DoIP header
+/// carries `protocol_version` instead of [`ProtocolVersion::DoIpIso`].
+///
+/// Example:
+/// ```
+/// use doip_rw::{write_message_versioned, message::{AliveCheckRequest, ProtocolVersion}};
+///
+/// let mut tcp = vec![];
+/// write_message_versioned(ProtocolVersion::Iso13400_2019, &AliveCheckRequest {}, &mut tcp).unwrap();
+/// assert_eq!(tcp, [0x03, 0xfc, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+/// ```
+pub fn write_message_versioned<W: Write, P: Payload>(
+    protocol_version: ProtocolVersion,
+    payload: &P,
+    writer: &mut W,
+) -> Result<(), DoIpError> {
+    let header =
+        DoIpHeader::new_versionned(protocol_version, P::payload_type(), payload.length() as u32);
+    let mut writer = io::CoalescingWriter::new(writer);
+    header.write(&mut writer)?;
+    payload.write(&mut writer)?;
+    Ok(writer.finish()?)
+}
+
+/// Writes a DoIP message with the protocol version of its header
+///
+/// The header is written with the protocol version of `framed.header`, while
+/// its payload type and length are computed from `framed.payload`, as in
+/// [`write_message()`]. See [`read_framed()`] for an example.
+pub fn write_framed<W: Write, P: Payload>(
+    framed: &Framed<P>,
+    writer: &mut W,
+) -> Result<(), DoIpError> {
+    let header = proto::header::header_for(framed.header.protocol_version, &framed.payload);
+    let mut writer = io::CoalescingWriter::new(writer);
+    header.write(&mut writer)?;
+    framed.payload.write(&mut writer)?;
+    Ok(writer.finish()?)
+}
+
+/// Encodes a DoIP header and its DoIp payload into a byte slice
+///
+/// This function is [`write_message()`] without any writer nor allocation :
+/// the message is encoded at the start of `buf`, and its length is returned.
+///
+/// If `buf` is shorter than [`length_message()`],
+/// [`DoIpError::BufferTooSmall`] is returned with the required length, and
+/// `buf` is left untouched.
+///
+/// Example:
+/// ```
+/// use doip_rw::{encode_message_into, message::AliveCheckResponse, DoIpError};
+///
+/// let mut frame = [0u8; 64];
+/// let response = AliveCheckResponse { source_address: 0x0e00 };
+/// let length = encode_message_into(&response, &mut frame)?;
+/// assert_eq!(frame[..length], [0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x0e, 0x00]);
+///
+/// let err = encode_message_into(&response, &mut frame[..4]).unwrap_err();
+/// assert!(matches!(err, DoIpError::BufferTooSmall { value: 4, expected: 10 }));
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub fn encode_message_into<P: Payload>(payload: &P, buf: &mut [u8]) -> Result<usize, DoIpError> {
+    let length = length_message(payload);
+    let message = wire::payload::payload_buffer(buf, length)?;
+    let (header, payload_buf) = message.split_at_mut(DOIP_HEADER_LENGTH);
+    header.copy_from_slice(&DoIpHeader::new(P::payload_type(), payload.length() as u32).to_bytes());
+    payload.encode_into(payload_buf)?;
+    Ok(length)
 }
 
 /// Length of a DoIp message in bytes
@@ -112,17 +386,40 @@ pub fn length_message<P: Payload>(payload: &P) -> usize {
 ///
 /// Example:
 /// ```
-/// use std::io::Cursor;
 /// use doip_rw::read_header;
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00];
 /// let hdr = read_header(&mut tcp).unwrap();
 /// ```
 pub fn read_header<R: Read>(reader: &mut R) -> Result<DoIpHeader, DoIpError> {
     DoIpHeader::read(reader)
 }
 
+/// Read and validate a DoIp header
+///
+/// This is [`read_header()`] followed by [`HeaderValidator::validate()`]. It
+/// should be used by a DoIP entity, so that a malformed or oversized message
+/// is rejected before its payload is read.
+///
+/// Example:
+/// ```
+/// use doip_rw::{read_header_validated, HeaderValidator};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff];
+/// let err = read_header_validated(&mut tcp, &HeaderValidator::new(4096)).unwrap_err();
+/// let nack = err.header_nack().unwrap();
+/// ```
+pub fn read_header_validated<R: Read>(
+    reader: &mut R,
+    validator: &HeaderValidator,
+) -> Result<DoIpHeader, DoIpError> {
+    let header = DoIpHeader::read(reader)?;
+    validator.validate(&header)?;
+    Ok(header)
+}
+
 /// Read a specific DoIp payload, specifying the exact payload through Payload type
 ///
 /// This function should normally be called after `read_header`, depending on the
@@ -130,11 +427,10 @@ pub fn read_header<R: Read>(reader: &mut R) -> Result<DoIpHeader, DoIpError> {
 ///
 /// Example: if the header payload_type == RoutingActivationResponse :
 /// ```
-/// use std::io::Cursor;
 /// use doip_rw::{read_header, read_payload, message::AliveCheckResponse};
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
 /// let hdr = read_header(&mut tcp).unwrap();
 /// let response : AliveCheckResponse = read_payload(&mut tcp, hdr.payload_length as usize).unwrap();
 /// ```
@@ -142,7 +438,8 @@ pub fn read_payload<R: Read, P: Payload>(
     reader: &mut R,
     payload_length: usize,
 ) -> Result<P, DoIpError> {
-    P::read(reader, payload_length)
+    let mut reader = io::PayloadReader::new(reader, P::payload_type(), payload_length)?;
+    P::read(&mut reader, payload_length)
 }
 
 /// Read a specific DoIp payload into an existing payload
@@ -154,14 +451,13 @@ pub fn read_payload<R: Read, P: Payload>(
 ///
 /// Example:
 /// ```
-/// use std::io::Cursor;
 /// use doip_rw::{read_header, read_payload, read_replace_payload, message::AliveCheckResponse};
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
 /// let hdr = read_header(&mut tcp).unwrap();
 /// let mut response : AliveCheckResponse = read_payload(&mut tcp, hdr.payload_length as usize).unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
 /// read_replace_payload(&mut response, &mut tcp, hdr.payload_length as usize).unwrap();
 /// ```
 pub fn read_replace_payload<R: Read, P: Payload>(
@@ -169,7 +465,8 @@ pub fn read_replace_payload<R: Read, P: Payload>(
     reader: &mut R,
     payload_length: usize,
 ) -> Result<(), DoIpError> {
-    payload.read_replace(reader, payload_length)
+    let mut reader = io::PayloadReader::new(reader, P::payload_type(), payload_length)?;
+    payload.read_replace(&mut reader, payload_length)
 }
 
 /// Read a specific DoIp message into an existing message
@@ -181,30 +478,174 @@ pub fn read_replace_payload<R: Read, P: Payload>(
 ///
 /// Example:
 /// ```
-/// use std::io::Cursor;
 /// use doip_rw::{read_header, read_payload, read_replace_message, message::AliveCheckResponse};
 ///
 /// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
 /// let hdr = read_header(&mut tcp).unwrap();
 /// let mut response : AliveCheckResponse = read_payload(&mut tcp, hdr.payload_length as usize).unwrap();
-/// let mut tcp = Cursor::new([0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54]);
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
 /// read_replace_message(&mut response, &mut tcp).unwrap();
 /// ```
 pub fn read_replace_message<R: Read, P: Payload>(
     payload: &mut P,
     reader: &mut R,
+) -> Result<(), DoIpError> {
+    read_replace_message_with(payload, reader, UnexpectedPayloadPolicy::Leave)
+}
+
+/// Read a specific DoIp message into an existing message, handling a message
+/// of another payload type according to `policy`
+///
+/// This function is [`read_replace_message()`] with the handling of
+/// [`read_message_with()`].
+pub fn read_replace_message_with<R: Read, P: Payload>(
+    payload: &mut P,
+    reader: &mut R,
+    policy: UnexpectedPayloadPolicy,
 ) -> Result<(), DoIpError> {
     let header = read_header(reader)?;
     if P::payload_type() == header.payload_type {
         read_replace_payload(payload, reader, header.payload_length as usize)
     } else {
-        Err(DoIpError::UnexpectedPayloadType {
-            value: header.payload_type.into_u16(),
-        })
+        Err(unexpected_payload(reader, header, policy))
     }
 }
 
+/// Reads a DoIP header and its DoIP payload, whatever the payload type
+///
+/// This function is the counterpart of [`read_message()`] when the next
+/// message type is not known beforehand. The payload is decoded according to
+/// the header payload type, and payload types not handled by this crate are
+/// returned as [`DoIpMessage::Unknown`].
+///
+/// Example:
+/// ```
+/// use doip_rw::{read_any_message, DoIpMessage};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00];
+/// match read_any_message(&mut tcp).unwrap() {
+///   DoIpMessage::AliveCheckRequest(_) => {},
+///   _ => {},
+/// }
+/// ```
+pub fn read_any_message<'a, R: Read>(reader: &mut R) -> Result<DoIpMessage<'a>, DoIpError> {
+    let header = read_header(reader)?;
+    read_any_payload(reader, &header)
+}
+
+/// Read any DoIp payload, according to an already read header
+///
+/// This function should normally be called after `read_header`, and decodes
+/// the payload according to the header `payload_type`.
+///
+/// Example:
+/// ```
+/// use doip_rw::{read_any_payload, read_header, DoIpMessage};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp: &[u8] = &[0x02, 0xfd, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x04, 0x54];
+/// let hdr = read_header(&mut tcp).unwrap();
+/// let msg = read_any_payload(&mut tcp, &hdr).unwrap();
+/// assert!(matches!(msg, DoIpMessage::AliveCheckResponse(_)));
+/// ```
+pub fn read_any_payload<'a, R: Read>(
+    reader: &mut R,
+    header: &DoIpHeader,
+) -> Result<DoIpMessage<'a>, DoIpError> {
+    let payload_length = header.payload_length as usize;
+    let mut reader = io::PayloadReader::new(reader, header.payload_type, payload_length)?;
+    DoIpMessage::read(&mut reader, header.payload_type, payload_length)
+}
+
+/// Decodes a whole DoIP message from a byte slice
+///
+/// The DoIP header at the start of `buf` is decoded, then its payload,
+/// without any allocation nor [`Read`] : the user data of the diagnostic
+/// messages are borrowed from `buf`. The returned length is the number of
+/// bytes of the message, header included, after which the next message
+/// starts.
+///
+/// If `buf` is shorter than the message, [`DoIpError::IncompleteMessage`] is
+/// returned, and if the message length does not even fit in a `usize`,
+/// [`DoIpError::MessageTooLarge`].
+///
+/// Example:
+/// ```
+/// use doip_rw::{parse_message, DoIpMessage};
+///
+/// let buf = [
+///     0x02, 0xfd, 0x80, 0x01, 0x00, 0x00, 0x00, 0x07, 0x0e, 0x00, 0x00, 0x77, 0x22, 0xf1, 0x90,
+///     0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00,
+/// ];
+/// let (_, message, length) = parse_message(&buf).unwrap();
+/// match message {
+///     DoIpMessage::DiagnosticMessage(m) => assert_eq!(m.user_data.get_ref(), &[0x22, 0xf1, 0x90]),
+///     _ => unreachable!(),
+/// }
+/// let (_, message, _) = parse_message(&buf[length..]).unwrap();
+/// assert!(matches!(message, DoIpMessage::AliveCheckRequest(_)));
+/// ```
+pub fn parse_message(buf: &[u8]) -> Result<(DoIpHeader, DoIpMessage<'_>, usize), DoIpError> {
+    let incomplete = |expected| DoIpError::IncompleteMessage {
+        value: buf.len(),
+        expected,
+    };
+    let header_bytes = buf.first_chunk().ok_or(incomplete(DOIP_HEADER_LENGTH))?;
+    let header = DoIpHeader::from_bytes(header_bytes);
+    let length = (header.payload_length as usize)
+        .checked_add(DOIP_HEADER_LENGTH)
+        .ok_or(DoIpError::MessageTooLarge {
+            value: header.payload_length,
+            max: (usize::MAX - DOIP_HEADER_LENGTH) as u32,
+        })?;
+    let payload = buf
+        .get(DOIP_HEADER_LENGTH..length)
+        .ok_or(incomplete(length))?;
+    let message = DoIpMessage::read_borrowed(payload, header.payload_type)?;
+    Ok((header, message, length))
+}
+
+/// Writes a DoIP header and the DoIp payload of any message
+///
+/// This function is the counterpart of [`write_message()`] for a
+/// [`DoIpMessage`].
+///
+/// Example:
+/// ```
+/// use doip_rw::{write_any_message, DoIpMessage, message::AliveCheckRequest};
+///
+/// // let mut tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let mut tcp = vec![];
+/// let msg = DoIpMessage::AliveCheckRequest(AliveCheckRequest {});
+/// write_any_message(&msg, &mut tcp).unwrap();
+/// ```
+pub fn write_any_message<W: Write>(message: &DoIpMessage, writer: &mut W) -> Result<(), DoIpError> {
+    write_any_message_versioned(ProtocolVersion::DoIpIso, message, writer)
+}
+
+/// Writes a DoIP header and the DoIp payload of any message, with a given
+/// protocol version
+///
+/// This function is the counterpart of [`write_message_versioned()`] for a
+/// [`DoIpMessage`].
+pub fn write_any_message_versioned<W: Write>(
+    protocol_version: ProtocolVersion,
+    message: &DoIpMessage,
+    writer: &mut W,
+) -> Result<(), DoIpError> {
+    let header = DoIpHeader::new_versionned(
+        protocol_version,
+        message.payload_type(),
+        message.length() as u32,
+    );
+    let mut writer = io::CoalescingWriter::new(writer);
+    header.write(&mut writer)?;
+    message.write(&mut writer)?;
+    Ok(writer.finish()?)
+}
+
 /// Module containing all the *messages* handled by the API.
 ///
 /// The [`read_message()`], [`read_payload()`], [`write_message()`] all rely on
@@ -213,6 +654,7 @@ pub mod message {
     // Export all the DoIp messages handled by the crate
     pub use super::proto::alive_check::*;
     pub use super::proto::diagnostic_message::*;
+    pub use super::proto::doip_message::*;
     pub use super::proto::entity_status::*;
     pub use super::proto::generic_header_nack::*;
     pub use super::proto::header::*;
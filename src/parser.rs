@@ -0,0 +1,337 @@
+use alloc::vec::Vec;
+
+use crate::io::{self, Read};
+use crate::{DoIpError, DoIpHeader, DoIpMessage, HeaderValidator, DOIP_HEADER_LENGTH};
+
+/// A decoded DoIP message, ie. its header and its payload.
+pub type Frame<'a> = (DoIpHeader, DoIpMessage<'a>);
+
+#[derive(Debug, Default)]
+/// Push based DoIP stream parser
+///
+/// The parser splits a byte stream received in chunks of any size into DoIP
+/// messages, without relying on any IO trait nor runtime. The received bytes
+/// are given to [`DoIpStreamParser::feed()`], and the complete messages are
+/// then retrieved with [`DoIpStreamParser::next_frame()`] until it returns
+/// `None`. Partial messages are kept buffered until the next chunk.
+///
+/// The returned frames borrow the user data of the diagnostic messages from
+/// the parser buffer, and must be dropped before the next chunk is fed.
+///
+/// Each header is checked with a [`HeaderValidator`] before the payload is
+/// buffered. When the header is rejected with
+/// [`DoIpError::UnknownPayloadType`] or [`DoIpError::MessageTooLarge`], the
+/// payload is discarded by the parser, even if it spans several chunks, so
+/// that the next message can still be decoded. Other header errors leave the
+/// stream in an unknown state, and the connection should be closed.
+///
+/// Example:
+/// ```
+/// use doip_rw::{DoIpMessage, DoIpStreamParser};
+///
+/// // Chunks as received from a socket, with any boundaries.
+/// let chunks: [&[u8]; 3] = [
+///     &[0x02, 0xfd, 0x00, 0x08, 0x00],
+///     &[0x00, 0x00, 0x02, 0x0e, 0x00, 0x02, 0xfd, 0x00, 0x07, 0x00, 0x00],
+///     &[0x00, 0x00],
+/// ];
+/// let mut parser = DoIpStreamParser::new();
+/// let mut messages = 0;
+/// for chunk in chunks {
+///     parser.feed(chunk);
+///     while let Some(frame) = parser.next_frame() {
+///         let (_header, message) = frame?;
+///         messages += 1;
+///     }
+/// }
+/// assert_eq!(messages, 2);
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub struct DoIpStreamParser {
+    validator: HeaderValidator,
+    buf: Vec<u8>,
+    start: usize,
+    discard: usize,
+}
+
+impl DoIpStreamParser {
+    /// Creates a [`DoIpStreamParser`] with the default [`HeaderValidator`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a [`DoIpStreamParser`] validating headers with `validator`.
+    pub fn with_validator(validator: HeaderValidator) -> Self {
+        Self {
+            validator,
+            ..Self::default()
+        }
+    }
+
+    /// Appends the received bytes `data` to the parser buffer.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.compact();
+        let discarded = self.discard.min(data.len());
+        self.discard -= discarded;
+        self.buf.extend_from_slice(&data[discarded..]);
+    }
+
+    /// Get the number of buffered bytes, not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Decodes the next complete message.
+    ///
+    /// Returns `None` if no complete message is buffered, in which case more
+    /// bytes should be fed.
+    pub fn next_frame(&mut self) -> Option<Result<Frame<'_>, DoIpError>> {
+        self.next_raw().map(|raw| {
+            let (header, payload) = raw?;
+            let message = DoIpMessage::read_borrowed(payload, header.payload_type)?;
+            Ok((header, message))
+        })
+    }
+
+    /// Splits the next complete message into its header and its payload.
+    pub(crate) fn next_raw(&mut self) -> Option<Result<(DoIpHeader, &[u8]), DoIpError>> {
+        let available = self.buffered();
+        let header = DoIpHeader::from_bytes(self.buf[self.start..].first_chunk()?);
+        let length = match self.message_length(&header) {
+            Ok(length) => length,
+            Err(e) => {
+                if matches!(
+                    e,
+                    DoIpError::UnknownPayloadType(_) | DoIpError::MessageTooLarge { .. }
+                ) {
+                    // The header, and as much of the payload as buffered.
+                    let payload_length = header.payload_length as usize;
+                    let discarded = (available - DOIP_HEADER_LENGTH).min(payload_length);
+                    self.start += DOIP_HEADER_LENGTH + discarded;
+                    self.discard = payload_length - discarded;
+                }
+                return Some(Err(e));
+            }
+        };
+        if available < length {
+            return None;
+        }
+
+        let payload = &self.buf[self.start + DOIP_HEADER_LENGTH..self.start + length];
+        self.start += length;
+        Some(Ok((header, payload)))
+    }
+
+    /// Get the number of bytes missing before [`DoIpStreamParser::next_frame()`]
+    /// returns something, ie. 0 if a message or an error is ready.
+    pub(crate) fn missing(&self) -> usize {
+        let available = self.buffered();
+        let Some(header_bytes) = self.buf[self.start..].first_chunk() else {
+            return DOIP_HEADER_LENGTH - available;
+        };
+        let header = DoIpHeader::from_bytes(header_bytes);
+        match self.message_length(&header) {
+            Ok(length) => length.saturating_sub(available),
+            Err(_) => 0,
+        }
+    }
+
+    /// Validates `header`, and returns the length of its message, header
+    /// included.
+    fn message_length(&self, header: &DoIpHeader) -> Result<usize, DoIpError> {
+        self.validator.validate(header)?;
+        (header.payload_length as usize)
+            .checked_add(DOIP_HEADER_LENGTH)
+            .ok_or(DoIpError::MessageTooLarge {
+                value: header.payload_length,
+                max: (usize::MAX - DOIP_HEADER_LENGTH) as u32,
+            })
+    }
+
+    /// Reads at most `len` bytes from `reader` directly into the parser
+    /// buffer, with a single read, and returns the number of bytes read.
+    pub(crate) fn fill<R: Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+    ) -> Result<usize, io::Error> {
+        self.compact();
+        let end = self.buf.len();
+        self.buf.resize(end + len, 0);
+        let read = reader.read(&mut self.buf[end..]);
+        let n = *read.as_ref().unwrap_or(&0);
+        self.buf.truncate(end + n);
+        let discarded = self.discard.min(n);
+        self.discard -= discarded;
+        self.buf.drain(end..end + discarded);
+        read
+    }
+
+    /// Drops the already decoded bytes from the buffer.
+    fn compact(&mut self) {
+        self.buf.drain(..self.start);
+        self.start = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{write_message, Payload, PayloadType};
+    use alloc::vec;
+
+    fn encoded<P: Payload>(payload: &P) -> Vec<u8> {
+        let mut v = vec![];
+        write_message(payload, &mut v).unwrap();
+        v
+    }
+
+    fn diag() -> DiagnosticMessage<'static> {
+        DiagnosticMessage {
+            source_address: 0x0123,
+            target_address: 0x00ed,
+            user_data: UdsBuffer::Borrowed(&[0x22, 0xf0, 0x12]),
+        }
+    }
+
+    #[test]
+    fn partial_frames() {
+        let mut stream = encoded(&diag());
+        stream.extend(encoded(&AliveCheckRequest {}));
+
+        let mut parser = DoIpStreamParser::new();
+        let mut frames = vec![];
+        for b in stream {
+            parser.feed(&[b]);
+            if let Some(frame) = parser.next_frame() {
+                let (header, message) = frame.unwrap();
+                frames.push((header.payload_type, message.length()));
+            }
+            assert!(parser.next_frame().is_none());
+        }
+        assert_eq!(parser.buffered(), 0);
+        assert_eq!(
+            frames,
+            vec![
+                (PayloadType::DiagnosticMessage, diag().length()),
+                (PayloadType::AliveCheckRequest, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesced_frames() {
+        let mut stream = encoded(&diag());
+        stream.extend(encoded(&AliveCheckRequest {}));
+        stream.extend(encoded(&diag()));
+        stream.extend(&encoded(&AliveCheckRequest {})[..3]);
+
+        let mut parser = DoIpStreamParser::new();
+        parser.feed(&stream);
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(message, DoIpMessage::DiagnosticMessage(diag()));
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(message, DoIpMessage::DiagnosticMessage(diag()));
+        assert!(parser.next_frame().is_none());
+        assert_eq!(parser.buffered(), 3);
+
+        parser.feed(&encoded(&AliveCheckRequest {})[3..]);
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+        assert!(parser.next_frame().is_none());
+    }
+
+    #[test]
+    fn discards_too_large() {
+        let large = DiagnosticMessage {
+            source_address: 0x0123,
+            target_address: 0x00ed,
+            user_data: UdsBuffer::Owned(vec![0x36; 64]),
+        };
+        let mut stream = encoded(&large);
+        stream.extend(encoded(&AliveCheckRequest {}));
+
+        let mut parser = DoIpStreamParser::with_validator(HeaderValidator::new(16));
+        parser.feed(&stream[..20]);
+        assert!(matches!(
+            parser.next_frame(),
+            Some(Err(DoIpError::MessageTooLarge { value: 68, max: 16 }))
+        ));
+        assert!(parser.next_frame().is_none());
+        parser.feed(&stream[20..40]);
+        assert!(parser.next_frame().is_none());
+        assert_eq!(parser.buffered(), 0);
+        parser.feed(&stream[40..]);
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+    }
+
+    #[test]
+    fn max_payload_length() {
+        let header = [0x02, 0xfd, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff];
+        let mut parser = DoIpStreamParser::with_validator(HeaderValidator::new(u32::MAX));
+        parser.feed(&header);
+        assert!(matches!(
+            parser.next_frame(),
+            None | Some(Err(DoIpError::MessageTooLarge { .. }))
+        ));
+
+        let mut parser = DoIpStreamParser::new();
+        parser.feed(&header);
+        parser.feed(&[0x0e, 0x00]);
+        assert!(matches!(
+            parser.next_frame(),
+            Some(Err(DoIpError::MessageTooLarge {
+                value: 0xffff_ffff,
+                ..
+            }))
+        ));
+        assert_eq!(parser.buffered(), 0);
+        assert!(parser.next_frame().is_none());
+    }
+
+    #[test]
+    fn discards_unknown_payload_type() {
+        let mut stream = vec![
+            0x02, 0xfd, // Protocol version
+            0xf0, 0x12, // Payload type
+            0x00, 0x00, 0x00, 0x03, // Payload length
+            0x01, 0x02, 0x03,
+        ];
+        stream.extend(encoded(&AliveCheckRequest {}));
+
+        let mut parser = DoIpStreamParser::new();
+        parser.feed(&stream);
+        assert!(matches!(
+            parser.next_frame(),
+            Some(Err(DoIpError::UnknownPayloadType(0xf012)))
+        ));
+        let (_, message) = parser.next_frame().unwrap().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+    }
+
+    #[test]
+    fn incorrect_pattern() {
+        let mut parser = DoIpStreamParser::new();
+        parser.feed(&[0x02, 0xfc, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+        assert!(matches!(
+            parser.next_frame(),
+            Some(Err(DoIpError::IncorrectPatternFormat { .. }))
+        ));
+    }
+}
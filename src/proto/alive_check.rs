@@ -1,13 +1,21 @@
 use crate::LogicalAddress;
+use core::mem::size_of;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Alive check request message
 ///
 /// The alive check request is a message sent by the DoIp entity tested to the
 /// external tester to verify the tester is still plugged in.
 pub struct AliveCheckRequest {}
 
+impl AliveCheckRequest {
+    /// Length of the payload.
+    pub const LENGTH: usize = 0;
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Alive check response message
 ///
 /// The alive check response is a message sent by the DoIp external tester to the
@@ -16,3 +24,8 @@ pub struct AliveCheckResponse {
     /// Logical address of the tester
     pub source_address: LogicalAddress,
 }
+
+impl AliveCheckResponse {
+    /// Length of the payload.
+    pub const LENGTH: usize = size_of::<LogicalAddress>();
+}
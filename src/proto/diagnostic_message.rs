@@ -1,6 +1,10 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
 use crate::LogicalAddress;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Diagnostic message
 ///
 /// This is the main purpose of DoIp, ie. to convey a [`DiagnosticMessage`] from
@@ -10,7 +14,10 @@ use crate::LogicalAddress;
 /// It is the only DoIp message which can be big in size, as the UDS carried
 /// data can be quite large, especially for TransferDownload UDS
 /// requests. Therefore, the [`UdsBuffer`](enum@crate::message::UdsBuffer) is
-/// special, as it can be either owning data or borrowing data.
+/// special, as it can be either owning data or borrowing data. For user data
+/// too large to be buffered, the message can also be streamed with
+/// [`DiagnosticMessageReader`](crate::DiagnosticMessageReader) and
+/// [`write_diagnostic_message_from()`](crate::write_diagnostic_message_from).
 ///
 /// When using DoIP gateways, the `target_address` might be inconsistent with
 /// the IP target address, in the sense that the DoIP entity target address is
@@ -26,7 +33,36 @@ pub struct DiagnosticMessage<'a> {
     pub user_data: UdsBuffer<'a>,
 }
 
+impl<'a> DiagnosticMessage<'a> {
+    /// Length of the payload without user data.
+    pub const MIN_LENGTH: usize = size_of::<LogicalAddress>() + size_of::<LogicalAddress>();
+
+    /// Creates a [`DiagnosticMessage`] carrying `user_data`, either owned or
+    /// borrowed.
+    ///
+    /// Example:
+    /// ```
+    /// use doip_rw::message::DiagnosticMessage;
+    ///
+    /// let request = DiagnosticMessage::new(0x0e00, 0x0077, vec![0x22, 0xf1, 0x90]);
+    /// let transfer_data = [0x36; 4096];
+    /// let transfer = DiagnosticMessage::new(0x0e00, 0x0077, &transfer_data[..]);
+    /// ```
+    pub fn new(
+        source_address: LogicalAddress,
+        target_address: LogicalAddress,
+        user_data: impl Into<UdsBuffer<'a>>,
+    ) -> Self {
+        Self {
+            source_address,
+            target_address,
+            user_data: user_data.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// Positive acknowledgement code for [`DiagnosticMessagePositiveAck`].
 pub enum DiagnosticMessagePositiveAckCode {
@@ -40,6 +76,7 @@ pub enum DiagnosticMessagePositiveAckCode {
 ///
 /// Message sent by the DoIP entity to the DoIP external tester to notify it that the previous [`DiagnosticMessage`] was successfully received, and parsed.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiagnosticMessagePositiveAck<'a> {
     /// Logical address of the sender.
     pub source_address: LogicalAddress,
@@ -51,8 +88,26 @@ pub struct DiagnosticMessagePositiveAck<'a> {
     pub previous_diagnostic_message_data: UdsBuffer<'a>,
 }
 
+impl<'a> DiagnosticMessagePositiveAck<'a> {
+    /// Length of the payload without previous diagnostic message data.
+    pub const MIN_LENGTH: usize =
+        size_of::<LogicalAddress>() + size_of::<LogicalAddress>() + size_of::<u8>();
+
+    /// Creates a [`DiagnosticMessagePositiveAck`] acknowledging the reception
+    /// of a diagnostic message, without repeating it.
+    pub fn new(source_address: LogicalAddress, target_address: LogicalAddress) -> Self {
+        Self {
+            source_address,
+            target_address,
+            ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
+            previous_diagnostic_message_data: UdsBuffer::Borrowed(&[]),
+        }
+    }
+}
+
 /// Negative acknowledgement of diagnostic message.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiagnosticMessageNegativeAck<'a> {
     /// Logical address of the sender
     pub source_address: LogicalAddress,
@@ -64,7 +119,29 @@ pub struct DiagnosticMessageNegativeAck<'a> {
     pub previous_diagnostic_message_data: UdsBuffer<'a>,
 }
 
+impl<'a> DiagnosticMessageNegativeAck<'a> {
+    /// Length of the payload without previous diagnostic message data.
+    pub const MIN_LENGTH: usize =
+        size_of::<LogicalAddress>() + size_of::<LogicalAddress>() + size_of::<u8>();
+
+    /// Creates a [`DiagnosticMessageNegativeAck`] rejecting a diagnostic
+    /// message with `ack_code`, without repeating it.
+    pub fn new(
+        source_address: LogicalAddress,
+        target_address: LogicalAddress,
+        ack_code: DiagnosticMessageNegativeAckCode,
+    ) -> Self {
+        Self {
+            source_address,
+            target_address,
+            ack_code,
+            previous_diagnostic_message_data: UdsBuffer::Borrowed(&[]),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// Negative acknowledgement code for [`DiagnosticMessageNegativeAck`]
 pub enum DiagnosticMessageNegativeAckCode {
@@ -112,3 +189,15 @@ impl UdsBuffer<'_> {
         }
     }
 }
+
+impl From<Vec<u8>> for UdsBuffer<'_> {
+    fn from(data: Vec<u8>) -> Self {
+        UdsBuffer::Owned(data)
+    }
+}
+
+impl<'a> From<&'a [u8]> for UdsBuffer<'a> {
+    fn from(data: &'a [u8]) -> Self {
+        UdsBuffer::Borrowed(data)
+    }
+}
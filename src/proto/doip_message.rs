@@ -0,0 +1,92 @@
+use crate::message::*;
+use crate::PayloadType;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Any DoIP message
+///
+/// This enum holds one of the [`crate::message`] payloads, without knowing
+/// beforehand which one. It is the result of [`read_any_message()`](fn@crate::read_any_message),
+/// which dispatches on the [`PayloadType`] of the received
+/// [`DoIpHeader`](struct@crate::DoIpHeader).
+///
+/// Payload types which are not handled by this crate, such as vehicle
+/// manufacturer specific ones, are kept as raw bytes in
+/// [`DoIpMessage::Unknown`].
+pub enum DoIpMessage<'a> {
+    /// Generic DoIP header negative acknowledge.
+    GenericDoIpHeaderNegativeAcknowledge(GenericDoIpHeaderNegativeAcknowledge),
+    /// Vehicle identification request.
+    VehicleIdentificationRequest(VehicleIdentificationRequest),
+    /// Vehicle identification request with EID.
+    VehicleIdentificationRequestWithEid(VehicleIdentificationRequestWithEid),
+    /// Vehicle identification request with VIN.
+    VehicleIdentificationRequestWithVin(VehicleIdentificationRequestWithVin),
+    /// Vehicle identification response, or vehicle announcement.
+    VehicleIdentificationResponse(VehicleIdentificationResponse),
+    /// Routing activation request.
+    RoutingActivationRequest(RoutingActivationRequest),
+    /// Routing activation response.
+    RoutingActivationResponse(RoutingActivationResponse),
+    /// Alive check request.
+    AliveCheckRequest(AliveCheckRequest),
+    /// Alive check response.
+    AliveCheckResponse(AliveCheckResponse),
+    /// DoIP entity status request.
+    EntityStatusRequest(EntityStatusRequest),
+    /// DoIP entity status response.
+    EntityStatusResponse(EntityStatusResponse),
+    /// Diagnostic power mode information request.
+    PowerModeRequest(PowerModeRequest),
+    /// Diagnostic power mode information response.
+    PowerModeResponse(PowerModeResponse),
+    /// Diagnostic message.
+    DiagnosticMessage(DiagnosticMessage<'a>),
+    /// Diagnostic message positive acknowledgement.
+    DiagnosticMessagePositiveAck(DiagnosticMessagePositiveAck<'a>),
+    /// Diagnostic message negative acknowledgement.
+    DiagnosticMessageNegativeAck(DiagnosticMessageNegativeAck<'a>),
+    /// Payload type not handled by this crate, with its raw payload.
+    Unknown {
+        /// Payload type found in the DoIP header.
+        payload_type: PayloadType,
+        /// Raw payload bytes.
+        bytes: UdsBuffer<'a>,
+    },
+}
+
+impl DoIpMessage<'_> {
+    /// Get the payload type of this message.
+    pub fn payload_type(&self) -> PayloadType {
+        use DoIpMessage::*;
+        match self {
+            GenericDoIpHeaderNegativeAcknowledge(_) => {
+                PayloadType::GenericDoIpHeaderNegativeAcknowledge
+            }
+            VehicleIdentificationRequest(_) => PayloadType::VehicleIdentificationRequest,
+            VehicleIdentificationRequestWithEid(_) => {
+                PayloadType::VehicleIdentificationRequestWithEid
+            }
+            VehicleIdentificationRequestWithVin(_) => {
+                PayloadType::VehicleIdentificationRequestWithVin
+            }
+            VehicleIdentificationResponse(_) => PayloadType::VehicleIdentificationResponse,
+            RoutingActivationRequest(_) => PayloadType::RoutingActivationRequest,
+            RoutingActivationResponse(_) => PayloadType::RoutingActivationResponse,
+            AliveCheckRequest(_) => PayloadType::AliveCheckRequest,
+            AliveCheckResponse(_) => PayloadType::AliveCheckResponse,
+            EntityStatusRequest(_) => PayloadType::DoIpEntityStatusRequest,
+            EntityStatusResponse(_) => PayloadType::DoIpEntityStatusResponse,
+            PowerModeRequest(_) => PayloadType::DiagnosticPowerModeInformationRequest,
+            PowerModeResponse(_) => PayloadType::DiagnosticPowerModeInformationResponse,
+            DiagnosticMessage(_) => PayloadType::DiagnosticMessage,
+            DiagnosticMessagePositiveAck(_) => {
+                PayloadType::DiagnosticMessagePositiveAcknowledgement
+            }
+            DiagnosticMessageNegativeAck(_) => {
+                PayloadType::DiagnosticMessageNegativeAcknowledgement
+            }
+            Unknown { payload_type, .. } => *payload_type,
+        }
+    }
+}
@@ -1,19 +1,91 @@
+use core::mem::size_of;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Entity status request message
 pub struct EntityStatusRequest {}
 
+impl EntityStatusRequest {
+    /// Length of the payload.
+    pub const LENGTH: usize = 0;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// NodeType
+///
+/// This is a field in [`EntityStatusResponse`], telling whether the DoIP
+/// entity is a gateway or a node.
+pub enum NodeType {
+    /// DoIP gateway.
+    Gateway,
+    /// DoIP node.
+    Node,
+    /// Reserved.
+    Reserved(u8),
+}
+
 /// Entity status response
 ///
 /// This response from the DoIP entity gives to the DoIP external tester
 /// information about the limitations of the DoIP entity.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityStatusResponse {
     /// Node type
-    pub node_type: u8,
+    pub node_type: NodeType,
     /// Maximum number of connections supported.
     pub max_open_sockets: u8,
     /// Current number of connections opened.
     pub cur_open_sockets: u8,
-    /// Maximum length of a DoIP message which can be received.
-    pub max_data_size: u32,
+    /// Maximum length of a DoIP message which can be received, if provided.
+    pub max_data_size: Option<u32>,
+}
+
+impl EntityStatusResponse {
+    /// Length of the payload, without the maximum data size.
+    pub const LENGTH: usize = size_of::<u8>() + size_of::<u8>() + size_of::<u8>();
+    /// Length of the payload, with the maximum data size.
+    pub const MAX_DATA_SIZE_LENGTH: usize = Self::LENGTH + size_of::<u32>();
+
+    /// Creates an [`EntityStatusResponseBuilder`] for a DoIP entity of
+    /// `node_type`, accepting up to `max_open_sockets` connections.
+    pub fn builder(node_type: NodeType, max_open_sockets: u8) -> EntityStatusResponseBuilder {
+        EntityStatusResponseBuilder {
+            response: EntityStatusResponse {
+                node_type,
+                max_open_sockets,
+                cur_open_sockets: 0,
+                max_data_size: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Builder of an [`EntityStatusResponse`]
+///
+/// By default, the response has no connection opened, and no maximum data
+/// size.
+pub struct EntityStatusResponseBuilder {
+    response: EntityStatusResponse,
+}
+
+impl EntityStatusResponseBuilder {
+    /// Sets the current number of connections opened.
+    pub fn cur_open_sockets(mut self, cur_open_sockets: u8) -> Self {
+        self.response.cur_open_sockets = cur_open_sockets;
+        self
+    }
+
+    /// Sets the maximum length of a DoIP message which can be received.
+    pub fn max_data_size(mut self, max_data_size: u32) -> Self {
+        self.response.max_data_size = Some(max_data_size);
+        self
+    }
+
+    /// Builds the [`EntityStatusResponse`].
+    pub fn build(self) -> EntityStatusResponse {
+        self.response
+    }
 }
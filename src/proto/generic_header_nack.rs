@@ -1,4 +1,7 @@
+use core::mem::size_of;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// A DoIP Nack response specifier
 ///
@@ -20,7 +23,13 @@ pub enum NegativeAckCode {
 
 /// Generic DoIp Header Negative Acknowledgement
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GenericDoIpHeaderNegativeAcknowledge {
     /// The nack reason code
     pub nack_code: NegativeAckCode,
 }
+
+impl GenericDoIpHeaderNegativeAcknowledge {
+    /// Length of the payload.
+    pub const LENGTH: usize = size_of::<u8>();
+}
@@ -1,11 +1,12 @@
-use crate::PayloadType;
+use crate::{Payload, PayloadType};
 use core::mem::size_of;
 
 /// Length of the DoIP header.
 pub const DOIP_HEADER_LENGTH: usize =
     size_of::<u8>() + size_of::<u8>() + size_of::<u16>() + size_of::<u32>(); // 8 byte
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Generic DoIP header data structure.
 ///
 /// This header always preceeds a DoIP payload
@@ -15,6 +16,8 @@ pub struct DoIpHeader {
     /// - 0x01: ISO13400-2:2010.
     /// - 0x02: ISO13400-2:2012.
     /// - 0x03: ISO13400-2:2019.
+    /// - 0x04: following ISO13400-2:2019.
+    /// - 0xFF: default, only for vehicle identification requests.
     pub protocol_version: u8,
     /// The complement-to-1 to [`DoIpHeader::protocol_version`] field.
     pub inverse_protocol_version: u8,
@@ -51,14 +54,181 @@ impl DoIpHeader {
     }
 }
 
+/// Header of `payload` with the raw `protocol_version`.
+pub(crate) fn header_for<P: Payload>(protocol_version: u8, payload: &P) -> DoIpHeader {
+    DoIpHeader {
+        protocol_version,
+        inverse_protocol_version: !protocol_version,
+        payload_type: P::payload_type(),
+        payload_length: payload.length() as u32,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A DoIP payload with the header it was received or is sent with
+///
+/// This is the result of [`read_framed()`](crate::read_framed), which keeps
+/// the header of the received message, and notably the protocol version used
+/// by the peer. A response should usually carry the same protocol version,
+/// which is what [`Framed::reply()`] does.
+///
+/// When written with [`write_framed()`](crate::write_framed), only the
+/// protocol version of the header is used, while the payload type and length
+/// are computed from the payload.
+pub struct Framed<P> {
+    /// DoIP header of the message.
+    pub header: DoIpHeader,
+    /// DoIP payload of the message.
+    pub payload: P,
+}
+
+impl<P: Payload> Framed<P> {
+    /// Creates a [`Framed`] payload, sent with `protocol_version`.
+    pub fn new(protocol_version: ProtocolVersion, payload: P) -> Self {
+        Self {
+            header: header_for(protocol_version as u8, &payload),
+            payload,
+        }
+    }
+
+    /// Creates a [`Framed`] response to this message, with the same protocol
+    /// version.
+    ///
+    /// A vehicle identification request may be sent with the default
+    /// protocol version (0xFF), which must not be mirrored : the response is
+    /// then sent with [`ProtocolVersion::DoIpIso`]. To answer with another
+    /// protocol version, create the response with [`Framed::new()`].
+    pub fn reply<Q: Payload>(&self, payload: Q) -> Framed<Q> {
+        let protocol_version = match self.header.protocol_version {
+            v if v == ProtocolVersion::VehicleIdentificationRequest as u8 => {
+                ProtocolVersion::DoIpIso as u8
+            }
+            v => v,
+        };
+        Framed {
+            header: header_for(protocol_version, &payload),
+            payload,
+        }
+    }
+}
+
+/// Default maximum payload length accepted by [`HeaderValidator`].
+pub const DEFAULT_MAX_PAYLOAD_LENGTH: u32 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// DoIP header validator.
+///
+/// A received [`DoIpHeader`] should be checked before its payload is read, as
+/// required by ISO 13400-2 generic header handling. The validator checks, in
+/// order :
+/// - the protocol version and its inverse
+/// - the payload type is supported
+/// - the payload length doesn't exceed [`HeaderValidator::max_payload_length`]
+/// - the payload length is compatible with the payload type
+///
+/// Each failed check is reported with a distinct
+/// [`DoIpError`](enum@crate::DoIpError), which can be turned into the
+/// appropriate negative acknowledge with
+/// [`DoIpError::header_nack()`](crate::DoIpError::header_nack).
+pub struct HeaderValidator {
+    /// Maximum accepted payload length, usually the DoIP entity maximum data
+    /// size.
+    pub max_payload_length: u32,
+}
+
+impl HeaderValidator {
+    /// Creates a [`HeaderValidator`] accepting payloads up to
+    /// `max_payload_length` bytes.
+    pub fn new(max_payload_length: u32) -> Self {
+        Self { max_payload_length }
+    }
+}
+
+impl Default for HeaderValidator {
+    /// Creates a [`HeaderValidator`] accepting payloads up to
+    /// [`DEFAULT_MAX_PAYLOAD_LENGTH`] bytes.
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PAYLOAD_LENGTH)
+    }
+}
+
 #[repr(u8)]
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// DoIP protocol versions.
 pub enum ProtocolVersion {
-    /// DoIP ISO Dis
+    /// DoIP ISO Dis, ie. ISO 13400-2:2010
     DoIpIsoDis = 0x1,
-    /// DoIP ISO
+    /// DoIP ISO, ie. ISO 13400-2:2012
     DoIpIso = 0x2,
+    /// ISO 13400-2:2019
+    Iso13400_2019 = 0x3,
+    /// Protocol version following ISO 13400-2:2019
+    DoIpIsoV4 = 0x4,
     /// VehicleIdentificationRequest
     VehicleIdentificationRequest = 0xFF,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Protocol version sent on a connection.
+///
+/// The protocol version can either be fixed, or learned from the first DoIP
+/// header received from the peer, so that a tester and an entity agree on the
+/// version the entity supports.
+pub enum VersionPolicy {
+    /// Always send this protocol version.
+    Fixed(ProtocolVersion),
+    /// Send this protocol version until a DoIP header is received from the
+    /// peer, and then send the peer protocol version.
+    Echo(ProtocolVersion),
+}
+
+impl VersionPolicy {
+    /// Protocol version to send.
+    pub fn version(&self) -> ProtocolVersion {
+        match *self {
+            VersionPolicy::Fixed(version) | VersionPolicy::Echo(version) => version,
+        }
+    }
+
+    /// Learns the peer protocol version from a received, valid header.
+    ///
+    /// For [`VersionPolicy::Echo`], the first header with a protocol version
+    /// other than the vehicle identification default one (0xFF) fixes the
+    /// version to send.
+    pub fn learn(&mut self, header: &DoIpHeader) {
+        if let VersionPolicy::Echo(_) = self {
+            match ProtocolVersion::try_from(header.protocol_version) {
+                Ok(ProtocolVersion::VehicleIdentificationRequest) | Err(_) => {}
+                Ok(version) => *self = VersionPolicy::Fixed(version),
+            }
+        }
+    }
+}
+
+impl Default for VersionPolicy {
+    /// Always send [`ProtocolVersion::DoIpIso`].
+    fn default() -> Self {
+        VersionPolicy::Fixed(ProtocolVersion::DoIpIso)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Handling of a message of another payload type than the expected one.
+///
+/// When [`read_message_with()`](crate::read_message_with) receives a header
+/// of another payload type, its payload is either left in the reader, or
+/// consumed so that the next message can be read.
+pub enum UnexpectedPayloadPolicy {
+    /// Leave the payload unread, and return
+    /// [`DoIpError::UnexpectedPayloadType`](crate::DoIpError::UnexpectedPayloadType).
+    /// The reader is not synchronized on a DoIP header anymore.
+    #[default]
+    Leave,
+    /// Read and discard the payload, and return
+    /// [`DoIpError::UnexpectedPayloadType`](crate::DoIpError::UnexpectedPayloadType).
+    Discard,
+    /// Read the payload, and return it with its header in
+    /// [`DoIpError::UnexpectedMessage`](crate::DoIpError::UnexpectedMessage).
+    Return,
+}
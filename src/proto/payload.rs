@@ -1,5 +1,6 @@
+use crate::io::{Read, Write};
+use crate::wire::payload::payload_buffer;
 use crate::DoIpError;
-use std::io::{Read, Write};
 
 /// A DoIP payload
 ///
@@ -29,17 +30,35 @@ pub trait Payload {
     fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError>
     where
         Self: Sized;
+    /// Encodes the DoIP payload at the start of `buf`, without any writer,
+    /// and returns its length.
+    ///
+    /// [`DoIpError::BufferTooSmall`] is returned if `buf` is shorter than
+    /// [`Payload::length()`].
+    ///
+    /// The default implementation encodes the payload with
+    /// [`Payload::write()`] into `buf`, and is overridden by the payloads of
+    /// this crate.
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError>
+    where
+        Self: Sized,
+    {
+        let mut writer = payload_buffer(buf, self.length())?;
+        self.write(&mut writer)?;
+        Ok(self.length())
+    }
 }
 
 /// A DoIP payload with references
 ///
-/// This trait is implemented by only
-/// [`DiagnosticMessage`](struct@crate::message::DiagnosticMessage) DoIP
-/// payload. Its purpose is to create a payload with references rather that
-/// owned data.
+/// This trait is implemented by each DoIP payload in the [`crate::message`]
+/// module. Its purpose is to decode a payload directly from a byte slice,
+/// without any allocation : the payloads carrying user data, such as
+/// [`DiagnosticMessage`](struct@crate::message::DiagnosticMessage), hold
+/// references to the slice rather than owned data.
 pub trait BorrowedPayload<'a> {
-    /// Reads from the reader `payload_length` bytes and decodes the message,
-    /// using borrowed reference on the input [`Payload`](trait@crate::Payload).
+    /// Decodes the message from the whole `payload` slice, using borrowed
+    /// references on it.
     fn read_borrowed(payload: &'a [u8]) -> Result<Self, DoIpError>
     where
         Self: Sized;
@@ -47,6 +66,7 @@ pub trait BorrowedPayload<'a> {
 
 /// Supported DoIP payload types.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PayloadType {
     /// GenericDoIpHeaderNegativeAcknowledge
     GenericDoIpHeaderNegativeAcknowledge,
@@ -1,10 +1,41 @@
+use core::mem::size_of;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Power Mode Request message.
 pub struct PowerModeRequest {}
 
+impl PowerModeRequest {
+    /// Length of the payload.
+    pub const LENGTH: usize = 0;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// PowerMode
+///
+/// This is a field in [`PowerModeResponse`], telling whether the vehicle is
+/// ready for diagnostics.
+pub enum PowerMode {
+    /// Not ready.
+    NotReady,
+    /// Ready.
+    Ready,
+    /// Not supported.
+    NotSupported,
+    /// Reserved.
+    Reserved(u8),
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Power Mode Response message.
 pub struct PowerModeResponse {
     /// Power Mode
-    pub power_mode: u8,
+    pub power_mode: PowerMode,
+}
+
+impl PowerModeResponse {
+    /// Length of the payload.
+    pub const LENGTH: usize = size_of::<u8>();
 }
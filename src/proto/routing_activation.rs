@@ -1,6 +1,8 @@
 use crate::LogicalAddress;
+use core::mem::size_of;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// Activation type.
 ///
@@ -15,6 +17,7 @@ pub enum ActivationType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Routing activation request message.
 ///
 /// This is usually the first message from a DoIP external tester to a DoIP
@@ -32,6 +35,7 @@ pub struct RoutingActivationRequest {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// Routing Activation Response Code
 ///
@@ -64,6 +68,7 @@ pub enum RoutingActivationResponseCode {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Routing activation response message.
 ///
 /// This is the reply to [`RoutingActivationRequest`], sent by a DoIP entity to
@@ -83,3 +88,111 @@ pub struct RoutingActivationResponse {
     /// OEM specific.
     pub oem_specific: Option<[u8; 4]>,
 }
+
+impl RoutingActivationRequest {
+    /// Length of the payload, without the OEM specific field.
+    pub const LENGTH: usize = size_of::<LogicalAddress>() + size_of::<u8>() + size_of::<[u8; 4]>();
+    /// Length of the payload, with the OEM specific field.
+    pub const OEM_LENGTH: usize = Self::LENGTH + size_of::<[u8; 4]>();
+
+    /// Creates a [`RoutingActivationRequestBuilder`] for the tester
+    /// `source_address`.
+    ///
+    /// Example:
+    /// ```
+    /// use doip_rw::message::{ActivationType, RoutingActivationRequest};
+    ///
+    /// let request = RoutingActivationRequest::builder(0x0e00)
+    ///     .activation_type(ActivationType::CentralSecurity)
+    ///     .oem([0x12, 0x34, 0x56, 0x78])
+    ///     .build();
+    /// assert_eq!(request.reserved, [0; 4]);
+    /// ```
+    pub fn builder(source_address: LogicalAddress) -> RoutingActivationRequestBuilder {
+        RoutingActivationRequestBuilder {
+            request: RoutingActivationRequest {
+                source_address,
+                activation_type: ActivationType::Default,
+                reserved: [0; 4],
+                reserved_oem: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Builder of a [`RoutingActivationRequest`]
+///
+/// By default, the request has the [`ActivationType::Default`] activation
+/// type, and no OEM specific field.
+pub struct RoutingActivationRequestBuilder {
+    request: RoutingActivationRequest,
+}
+
+impl RoutingActivationRequestBuilder {
+    /// Sets the activation type.
+    pub fn activation_type(mut self, activation_type: ActivationType) -> Self {
+        self.request.activation_type = activation_type;
+        self
+    }
+
+    /// Sets the OEM specific field.
+    pub fn oem(mut self, oem: [u8; 4]) -> Self {
+        self.request.reserved_oem = Some(oem);
+        self
+    }
+
+    /// Builds the [`RoutingActivationRequest`].
+    pub fn build(self) -> RoutingActivationRequest {
+        self.request
+    }
+}
+
+impl RoutingActivationResponse {
+    /// Length of the payload, without the OEM specific field.
+    pub const LENGTH: usize = size_of::<LogicalAddress>()
+        + size_of::<LogicalAddress>()
+        + size_of::<u8>()
+        + size_of::<[u8; 4]>();
+    /// Length of the payload, with the OEM specific field.
+    pub const OEM_LENGTH: usize = Self::LENGTH + size_of::<[u8; 4]>();
+
+    /// Creates a [`RoutingActivationResponseBuilder`] answering the tester
+    /// `logical_address_tester` with `code`.
+    pub fn builder(
+        logical_address_tester: LogicalAddress,
+        logical_address_of_doip_entity: LogicalAddress,
+        code: RoutingActivationResponseCode,
+    ) -> RoutingActivationResponseBuilder {
+        RoutingActivationResponseBuilder {
+            response: RoutingActivationResponse {
+                logical_address_tester,
+                logical_address_of_doip_entity,
+                routing_activation_response_code: code,
+                reserved_oem: [0; 4],
+                oem_specific: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Builder of a [`RoutingActivationResponse`]
+///
+/// By default, the response has no OEM specific field.
+pub struct RoutingActivationResponseBuilder {
+    response: RoutingActivationResponse,
+}
+
+impl RoutingActivationResponseBuilder {
+    /// Sets the OEM specific field.
+    pub fn oem(mut self, oem: [u8; 4]) -> Self {
+        self.response.oem_specific = Some(oem);
+        self
+    }
+
+    /// Builds the [`RoutingActivationResponse`].
+    pub fn build(self) -> RoutingActivationResponse {
+        self.response
+    }
+}
@@ -1,31 +1,226 @@
-use crate::{LogicalAddress, Vin};
+use core::fmt;
+use core::mem::size_of;
+use core::str::FromStr;
 
+use crate::{DoIpError, LogicalAddress};
+
+/// Length of a VIN.
+const VIN_LENGTH: usize = 17;
+/// Weights of the VIN characters in the check digit computation.
+const VIN_WEIGHTS: [u32; VIN_LENGTH] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+/// Position of the check digit in the VIN.
+const VIN_CHECK_DIGIT_POSITION: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A Vehicle Identifier Number
+///
+/// The VIN is usually built from its string representation with
+/// [`Vin::from_str()`](FromStr::from_str), which checks the ISO 3779
+/// characters, or with [`Vin::from_str_with_check_digit()`] which also checks
+/// the check digit, as used in North America.
+///
+/// The inner bytes are not checked, as a DoIP entity may send a VIN not
+/// configured yet, filled with `0x00` or `0xff`.
+///
+/// Example:
+/// ```
+/// use doip_rw::Vin;
+///
+/// let vin: Vin = "VF1YYYYYZTT000001".parse()?;
+/// assert_eq!(vin.to_string(), "VF1YYYYYZTT000001");
+/// assert!("VF1YYYYYZTT00000I".parse::<Vin>().is_err());
+/// assert!(Vin::from_str_with_check_digit("1M8GDM9AXKP042788").is_ok());
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub struct Vin(pub [u8; VIN_LENGTH]);
+
+impl Vin {
+    /// Parses a VIN, and checks its check digit (9th character).
+    pub fn from_str_with_check_digit(s: &str) -> Result<Self, DoIpError> {
+        let vin: Vin = s.parse()?;
+        let expected = vin.check_digit();
+        let value = vin.0[VIN_CHECK_DIGIT_POSITION];
+        if value != expected {
+            return Err(DoIpError::InvalidVinCheckDigit {
+                value: value as char,
+                expected: expected as char,
+            });
+        }
+        Ok(vin)
+    }
+
+    /// Computes the expected check digit of the VIN.
+    fn check_digit(&self) -> u8 {
+        let sum: u32 = self
+            .0
+            .iter()
+            .zip(VIN_WEIGHTS)
+            .map(|(&c, weight)| vin_character_value(c) * weight)
+            .sum();
+        match sum % 11 {
+            10 => b'X',
+            digit => b'0' + digit as u8,
+        }
+    }
+}
+
+/// Tells if `c` is allowed in a VIN by ISO 3779, ie. a digit or an uppercase
+/// letter other than I, O and Q.
+fn is_vin_character(c: u8) -> bool {
+    c.is_ascii_digit() || (c.is_ascii_uppercase() && !matches!(c, b'I' | b'O' | b'Q'))
+}
+
+/// Value of a VIN character in the check digit computation.
+fn vin_character_value(c: u8) -> u32 {
+    (match c {
+        b'0'..=b'9' => c - b'0',
+        b'A'..=b'H' => c - b'A' + 1,
+        b'J'..=b'N' => c - b'J' + 1,
+        b'P' => 7,
+        b'R' => 9,
+        b'S'..=b'Z' => c - b'S' + 2,
+        _ => 0,
+    }) as u32
+}
+
+impl FromStr for Vin {
+    type Err = DoIpError;
+
+    /// Parses a VIN, and checks its characters according to ISO 3779.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((position, character)) = s
+            .chars()
+            .enumerate()
+            .find(|&(_, c)| !c.is_ascii() || !is_vin_character(c as u8))
+        {
+            return Err(DoIpError::InvalidVinCharacter {
+                position,
+                character,
+            });
+        }
+        if s.len() != VIN_LENGTH {
+            return Err(DoIpError::InvalidVinLength(s.len()));
+        }
+        let mut vin = [0; VIN_LENGTH];
+        vin.copy_from_slice(s.as_bytes());
+        Ok(Vin(vin))
+    }
+}
+
+impl From<[u8; VIN_LENGTH]> for Vin {
+    fn from(vin: [u8; VIN_LENGTH]) -> Self {
+        Vin(vin)
+    }
+}
+
+impl AsRef<[u8]> for Vin {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Vin {
+    /// Formats the VIN as a string, or as hexadecimal digits if it is not
+    /// printable ASCII.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.iter().all(|b| b.is_ascii_graphic()) {
+            self.0
+                .iter()
+                .try_for_each(|&b| fmt::Write::write_char(f, b as char))
+        } else {
+            self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// A vehicle EID
-pub type Eid = [u8; 6];
+///
+/// The EID uniquely identifies a DoIP entity, and is usually the MAC address
+/// of its network interface.
+pub struct Eid(pub [u8; 6]);
+
+impl Eid {
+    /// Creates an EID from a MAC address.
+    pub const fn from_mac(mac: [u8; 6]) -> Self {
+        Eid(mac)
+    }
+}
+
+impl From<[u8; 6]> for Eid {
+    fn from(eid: [u8; 6]) -> Self {
+        Eid(eid)
+    }
+}
+
+impl AsRef<[u8]> for Eid {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Eid {
+    /// Formats the EID as a MAC address, eg. `00:1a:37:00:00:01`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
 /// A vehicle GID
 pub type Gid = [u8; 6];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Vehicle Identification Request.
 ///
 /// This is a monocast/broadcast sent by a DoIP external tester to find all DoIP
 /// entities available on this IPv4 address (which might by a broadcast address
 /// such as 255.255.255.255).
 pub struct VehicleIdentificationRequest {}
+
+impl VehicleIdentificationRequest {
+    /// Length of the payload.
+    pub const LENGTH: usize = 0;
+}
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Vehicle Identification Request with Eid.
 ///
-/// This is the same as [`VehicleIdentificationRequest`], but asking to have an
-/// [`Eid`] in the response.
-pub struct VehicleIdentificationRequestWithEid {}
+/// This is the same as [`VehicleIdentificationRequest`], but only the DoIP
+/// entity with the matching [`Eid`] is expected to respond.
+pub struct VehicleIdentificationRequestWithEid {
+    /// EID of the DoIP entity which should respond.
+    pub eid: Eid,
+}
+
+impl VehicleIdentificationRequestWithEid {
+    /// Length of the payload.
+    pub const LENGTH: usize = size_of::<Eid>();
+}
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Vehicle Identification Request with a VIN.
 ///
-/// This is the same as [`VehicleIdentificationRequest`], but asking to have an
-/// [`Vin`] in the response.
-pub struct VehicleIdentificationRequestWithVin {}
+/// This is the same as [`VehicleIdentificationRequest`], but only the DoIP
+/// entities of the vehicle with the matching [`Vin`] are expected to respond.
+pub struct VehicleIdentificationRequestWithVin {
+    /// VIN of the vehicle which should respond.
+    pub vin: Vin,
+}
+
+impl VehicleIdentificationRequestWithVin {
+    /// Length of the payload.
+    pub const LENGTH: usize = size_of::<Vin>();
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// VinGidSyncStatus.
 pub enum VinGidSyncStatus {
@@ -38,6 +233,7 @@ pub enum VinGidSyncStatus {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 /// FurtherActionRequired
 ///
@@ -55,6 +251,7 @@ pub enum FurtherActionRequired {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Vehicle identiifcation response / Vehicle announcement
 ///
 /// This is the response to [`VehicleIdentificationRequest`], sent by a DoIP
@@ -68,9 +265,148 @@ pub struct VehicleIdentificationResponse {
     pub eid: Eid,
     //// Unique group identification of entities within a vehicle.
     /// None when value not set (as indicated by `0x00` or `0xFF`).
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::hex_option"))]
     pub gid: Option<Gid>,
     /// Further action to be taken by the external tester.
     pub further_action: FurtherActionRequired,
     /// Indicates whether all entites have synced information about VIN or GID.
-    pub vin_gid_sync_status: VinGidSyncStatus,
+    /// None when the optional field is not present.
+    pub vin_gid_sync_status: Option<VinGidSyncStatus>,
+}
+
+impl VehicleIdentificationResponse {
+    /// Length of the payload, without the VIN/GID synchronization status.
+    pub const LENGTH: usize = size_of::<Vin>()
+        + size_of::<LogicalAddress>()
+        + size_of::<Eid>()
+        + size_of::<Gid>()
+        + size_of::<u8>();
+    /// Length of the payload, with the VIN/GID synchronization status.
+    pub const SYNC_LENGTH: usize = Self::LENGTH + size_of::<u8>();
+
+    /// Creates a [`VehicleIdentificationResponseBuilder`] for the DoIP entity
+    /// `logical_address`.
+    ///
+    /// Example:
+    /// ```
+    /// use doip_rw::message::{Eid, VehicleIdentificationResponse, VinGidSyncStatus};
+    ///
+    /// let announcement = VehicleIdentificationResponse::builder(
+    ///     "VF1YYYYYZTT000001".parse()?,
+    ///     0x0077,
+    ///     Eid::from_mac([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]),
+    /// )
+    /// .vin_gid_sync_status(VinGidSyncStatus::Synchronized)
+    /// .build();
+    /// assert_eq!(announcement.gid, None);
+    /// # Ok::<(), doip_rw::DoIpError>(())
+    /// ```
+    pub fn builder(
+        vin: Vin,
+        logical_address: LogicalAddress,
+        eid: Eid,
+    ) -> VehicleIdentificationResponseBuilder {
+        VehicleIdentificationResponseBuilder {
+            response: VehicleIdentificationResponse {
+                vin,
+                logical_address,
+                eid,
+                gid: None,
+                further_action: FurtherActionRequired::NoFurtherActionRequired,
+                vin_gid_sync_status: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Builder of a [`VehicleIdentificationResponse`]
+///
+/// By default, the response has no GID, requires no further action, and has
+/// no VIN/GID synchronization status.
+pub struct VehicleIdentificationResponseBuilder {
+    response: VehicleIdentificationResponse,
+}
+
+impl VehicleIdentificationResponseBuilder {
+    /// Sets the group identification.
+    pub fn gid(mut self, gid: Gid) -> Self {
+        self.response.gid = Some(gid);
+        self
+    }
+
+    /// Sets the further action required from the external tester.
+    pub fn further_action(mut self, further_action: FurtherActionRequired) -> Self {
+        self.response.further_action = further_action;
+        self
+    }
+
+    /// Sets the VIN/GID synchronization status.
+    pub fn vin_gid_sync_status(mut self, status: VinGidSyncStatus) -> Self {
+        self.response.vin_gid_sync_status = Some(status);
+        self
+    }
+
+    /// Builds the [`VehicleIdentificationResponse`].
+    pub fn build(self) -> VehicleIdentificationResponse {
+        self.response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn vin_from_str() {
+        let vin: Vin = "VF1YYYYYZTT000001".parse().unwrap();
+        assert_eq!(&vin.0, b"VF1YYYYYZTT000001");
+        assert!(matches!(
+            "VF1YYYYYZTT00001".parse::<Vin>(),
+            Err(DoIpError::InvalidVinLength(16))
+        ));
+        assert!(matches!(
+            "VF1YYYYYZTT000001X".parse::<Vin>(),
+            Err(DoIpError::InvalidVinLength(18))
+        ));
+        for (vin, position, character) in [
+            ("VF1YYYYYZTT00000I", 16, 'I'),
+            ("VF1YOYYYZTT000001", 4, 'O'),
+            ("QF1YYYYYZTT000001", 0, 'Q'),
+            ("vf1YYYYYZTT000001", 0, 'v'),
+            ("VF1YYYYYZTT00000é", 16, 'é'),
+        ] {
+            match vin.parse::<Vin>() {
+                Err(DoIpError::InvalidVinCharacter {
+                    position: p,
+                    character: c,
+                }) => assert_eq!((p, c), (position, character)),
+                r => panic!("unexpected result for {}: {:?}", vin, r),
+            }
+        }
+    }
+
+    #[test]
+    fn vin_check_digit() {
+        assert!(Vin::from_str_with_check_digit("1M8GDM9AXKP042788").is_ok());
+        assert!(Vin::from_str_with_check_digit("11111111111111111").is_ok());
+        assert!(matches!(
+            Vin::from_str_with_check_digit("1M8GDM9A1KP042788"),
+            Err(DoIpError::InvalidVinCheckDigit {
+                value: '1',
+                expected: 'X'
+            })
+        ));
+        assert!(Vin::from_str_with_check_digit("VF1YYYYYZTT00000I").is_err());
+    }
+
+    #[test]
+    fn display() {
+        let vin: Vin = "VF1YYYYYZTT000001".parse().unwrap();
+        assert_eq!(vin.to_string(), "VF1YYYYYZTT000001");
+        assert_eq!(Vin([0xff; 17]).to_string(), "ff".repeat(17));
+        let eid = Eid::from_mac([0x00, 0x1a, 0x37, 0x00, 0x00, 0x01]);
+        assert_eq!(eid.to_string(), "00:1a:37:00:00:01");
+    }
 }
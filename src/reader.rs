@@ -0,0 +1,257 @@
+use crate::io::{self, ErrorKind, Read};
+use crate::{DoIpError, DoIpStreamParser, Frame, HeaderValidator, Payload};
+
+/// Minimal number of bytes asked to the reader at once.
+const READ_SIZE: usize = 4096;
+
+/// Buffered DoIP reader
+///
+/// The reader receives the DoIP messages from an unbuffered transport, such as
+/// a `TcpStream`, with as few reads as possible: each read asks for at least
+/// 4096 bytes, or the whole remaining message if larger, so that a burst of
+/// messages is usually received with a single read. The received bytes are
+/// kept in an internal buffer across messages, and the messages are decoded
+/// from there.
+///
+/// Each header is checked with a [`HeaderValidator`], as in
+/// [`DoIpStreamParser`], before its payload is buffered.
+///
+/// Example:
+/// ```
+/// use doip_rw::message::{AliveCheckRequest, DiagnosticMessage};
+/// use doip_rw::{DoIpMessage, DoIpReader};
+///
+/// // let tcp = TcpStream::connect("127.0.0.1:13400").unwrap();
+/// let tcp: &[u8] = &[
+///     0x02, 0xfd, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, // Alive check request
+///     0x02, 0xfd, 0x80, 0x01, 0x00, 0x00, 0x00, 0x06, 0x00, 0x77, 0x0e, 0x00, 0x50, 0x01,
+/// ];
+/// let mut reader = DoIpReader::new(tcp);
+/// let (_header, message) = reader.read_frame()?;
+/// assert!(matches!(message, DoIpMessage::AliveCheckRequest(_)));
+/// let response: DiagnosticMessage = reader.read_message()?;
+/// assert_eq!(response.user_data.get_ref(), &[0x50, 0x01]);
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+#[derive(Debug)]
+pub struct DoIpReader<R> {
+    reader: R,
+    parser: DoIpStreamParser,
+}
+
+impl<R: Read> DoIpReader<R> {
+    /// Creates a [`DoIpReader`] with the default [`HeaderValidator`].
+    pub fn new(reader: R) -> Self {
+        Self::with_validator(reader, HeaderValidator::default())
+    }
+
+    /// Creates a [`DoIpReader`] validating headers with `validator`.
+    pub fn with_validator(reader: R, validator: HeaderValidator) -> Self {
+        Self {
+            reader,
+            parser: DoIpStreamParser::with_validator(validator),
+        }
+    }
+
+    /// Reads the next DoIP message, whatever its payload type.
+    ///
+    /// The user data of the diagnostic messages are borrowed from the internal
+    /// buffer. As with [`DoIpStreamParser`], the payload of a message rejected
+    /// with [`DoIpError::UnknownPayloadType`] or [`DoIpError::MessageTooLarge`]
+    /// is skipped, and the next message can still be read.
+    pub fn read_frame(&mut self) -> Result<Frame<'_>, DoIpError> {
+        self.receive()?;
+        self.parser
+            .next_frame()
+            .expect("a complete message is buffered")
+    }
+
+    /// Reads the next DoIP message, which must be of payload type `P`.
+    ///
+    /// A message of another payload type is skipped, and
+    /// [`DoIpError::UnexpectedPayloadType`] returned.
+    pub fn read_message<P: Payload>(&mut self) -> Result<P, DoIpError> {
+        self.receive()?;
+        let (header, mut payload) = self
+            .parser
+            .next_raw()
+            .expect("a complete message is buffered")?;
+        if P::payload_type() != header.payload_type {
+            return Err(DoIpError::UnexpectedPayloadType {
+                value: header.payload_type.into_u16(),
+            });
+        }
+        P::read(&mut payload, header.payload_length as usize)
+    }
+
+    /// Get the number of received bytes, not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.parser.buffered()
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader would lose the buffered
+    /// bytes.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwraps this [`DoIpReader`], returning the underlying reader.
+    ///
+    /// The buffered bytes are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads until a whole message, or a header error, is buffered.
+    fn receive(&mut self) -> Result<(), DoIpError> {
+        loop {
+            let missing = self.parser.missing();
+            if missing == 0 {
+                return Ok(());
+            }
+            match self.parser.fill(&mut self.reader, missing.max(READ_SIZE)) {
+                Ok(0) => return Err(io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(_) => {}
+                #[cfg(feature = "std")]
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{write_message, DoIpMessage, PayloadType};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Reader returning at most `chunk` bytes per read, and counting them.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        reads: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.reads += 1;
+            let len = buf.len().min(self.chunk);
+            self.data.read(&mut buf[..len])
+        }
+    }
+
+    fn stream() -> Vec<u8> {
+        let mut stream = vec![];
+        write_message(&AliveCheckRequest {}, &mut stream).unwrap();
+        write_message(
+            &DiagnosticMessage::new(0x0e00, 0x0077, vec![0x22, 0xf1, 0x90]),
+            &mut stream,
+        )
+        .unwrap();
+        write_message(
+            &AliveCheckResponse {
+                source_address: 0x0e00,
+            },
+            &mut stream,
+        )
+        .unwrap();
+        stream
+    }
+
+    #[test]
+    fn single_read() {
+        let stream = stream();
+        let mut reader = DoIpReader::new(ChunkedReader {
+            data: &stream,
+            chunk: usize::MAX,
+            reads: 0,
+        });
+        let (_, message) = reader.read_frame().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {})
+        );
+        let (_, message) = reader.read_frame().unwrap();
+        assert_eq!(
+            message,
+            DoIpMessage::DiagnosticMessage(DiagnosticMessage::new(
+                0x0e00,
+                0x0077,
+                &[0x22, 0xf1, 0x90][..]
+            ))
+        );
+        let response: AliveCheckResponse = reader.read_message().unwrap();
+        assert_eq!(response.source_address, 0x0e00);
+        assert_eq!(reader.get_ref().reads, 1);
+        assert_eq!(reader.buffered(), 0);
+        assert!(matches!(reader.read_frame(), Err(DoIpError::Io(_))));
+    }
+
+    #[test]
+    fn partial_reads() {
+        let stream = stream();
+        let mut reader = DoIpReader::new(ChunkedReader {
+            data: &stream,
+            chunk: 5,
+            reads: 0,
+        });
+        let mut payload_types = vec![];
+        while let Ok((header, _)) = reader.read_frame() {
+            payload_types.push(header.payload_type);
+        }
+        assert_eq!(
+            payload_types,
+            vec![
+                PayloadType::AliveCheckRequest,
+                PayloadType::DiagnosticMessage,
+                PayloadType::AliveCheckResponse,
+            ]
+        );
+    }
+
+    #[test]
+    fn unexpected_payload_type() {
+        let stream = stream();
+        let mut reader = DoIpReader::new(&stream[..]);
+        assert!(matches!(
+            reader.read_message::<AliveCheckResponse>(),
+            Err(DoIpError::UnexpectedPayloadType { value: 0x0007 })
+        ));
+        let request: DiagnosticMessage = reader.read_message().unwrap();
+        assert_eq!(request.user_data.get_ref(), &[0x22, 0xf1, 0x90]);
+    }
+
+    #[test]
+    fn skips_too_large() {
+        let mut stream = vec![];
+        write_message(
+            &DiagnosticMessage::new(0x0e00, 0x0077, vec![0x36; 64]),
+            &mut stream,
+        )
+        .unwrap();
+        write_message(&AliveCheckRequest {}, &mut stream).unwrap();
+        let mut reader = DoIpReader::with_validator(
+            ChunkedReader {
+                data: &stream,
+                chunk: 16,
+                reads: 0,
+            },
+            HeaderValidator::new(16),
+        );
+        assert!(matches!(
+            reader.read_frame(),
+            Err(DoIpError::MessageTooLarge { value: 68, max: 16 })
+        ));
+        let _: AliveCheckRequest = reader.read_message().unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+//! Serde representation of the DoIP messages
+//!
+//! The messages derive `Serialize` and `Deserialize` with the `serde`
+//! feature. Their fields keep the derived representation, except for :
+//! - the [`Vin`], rendered as a string
+//! - the [`Eid`] and GID, rendered as hexadecimal strings
+//! - the [`UdsBuffer`], rendered as a sequence of bytes, and always
+//!   deserialized as [`UdsBuffer::Owned`]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::message::{Eid, UdsBuffer};
+use crate::Vin;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        s.push(HEX_DIGITS[(b & 0x0f) as usize] as char);
+    }
+    s
+}
+
+fn from_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != 2 * N || !s.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (b, digits) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let digits = core::str::from_utf8(digits).ok()?;
+        *b = u8::from_str_radix(digits, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Fixed size identifiers, such as the GID, as hexadecimal strings.
+pub(crate) mod hex {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        from_hex(&s).ok_or_else(|| {
+            let mut msg = String::new();
+            let _ = write!(msg, "expected {} hexadecimal digits, got {:?}", 2 * N, s);
+            D::Error::custom(msg)
+        })
+    }
+}
+
+/// Optional fixed size identifiers, such as the GID, as hexadecimal strings.
+pub(crate) mod hex_option {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer, const N: usize>(
+        bytes: &Option<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&to_hex(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Option<[u8; N]>, D::Error> {
+        #[derive(Deserialize)]
+        struct Hex<const N: usize>(#[serde(with = "super::hex")] [u8; N]);
+        Ok(Option::<Hex<N>>::deserialize(deserializer)?.map(|Hex(bytes)| bytes))
+    }
+}
+
+impl Serialize for Vin {
+    /// Serializes the VIN as a string, or as 34 hexadecimal digits if it is
+    /// not printable ASCII, such as the `0xff` filled VIN of a DoIP entity
+    /// not configured yet.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Vin {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if let Ok(vin) = s.as_bytes().try_into() {
+            return Ok(Vin(vin));
+        }
+        from_hex(&s).map(Vin).ok_or_else(|| {
+            let mut msg = String::new();
+            let _ = write!(
+                msg,
+                "expected a 17 characters VIN or 34 hexadecimal digits, got {:?}",
+                s
+            );
+            D::Error::custom(msg)
+        })
+    }
+}
+
+impl Serialize for Eid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Eid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex::deserialize(deserializer).map(Eid)
+    }
+}
+
+impl Serialize for UdsBuffer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get_ref().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UdsBuffer<'_> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(UdsBuffer::Owned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::message::*;
+    use crate::{DoIpHeader, DoIpMessage, PayloadType};
+    use serde_json::json;
+
+    fn round_trip(message: &DoIpMessage) {
+        let json = serde_json::to_string(message).unwrap();
+        let decoded: DoIpMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(&decoded, message, "json: {}", json);
+    }
+
+    fn owned(data: &[u8]) -> UdsBuffer<'static> {
+        UdsBuffer::Owned(data.to_vec())
+    }
+
+    #[test]
+    fn round_trip_all_messages() {
+        let messages = [
+            DoIpMessage::GenericDoIpHeaderNegativeAcknowledge(
+                GenericDoIpHeaderNegativeAcknowledge {
+                    nack_code: NegativeAckCode::Reserved(0x42),
+                },
+            ),
+            DoIpMessage::VehicleIdentificationRequest(VehicleIdentificationRequest {}),
+            DoIpMessage::VehicleIdentificationRequestWithEid(VehicleIdentificationRequestWithEid {
+                eid: Eid([1, 2, 3, 4, 5, 6]),
+            }),
+            DoIpMessage::VehicleIdentificationRequestWithVin(VehicleIdentificationRequestWithVin {
+                vin: Vin(*b"VF1YYYYYZTT000001"),
+            }),
+            DoIpMessage::VehicleIdentificationResponse(VehicleIdentificationResponse {
+                vin: Vin([0xff; 17]),
+                logical_address: 0x0077,
+                eid: Eid([1, 2, 3, 4, 5, 6]),
+                gid: Some([6, 5, 4, 3, 2, 1]),
+                further_action: FurtherActionRequired::VmSpecific(0x12),
+                vin_gid_sync_status: Some(VinGidSyncStatus::Incomplete),
+            }),
+            DoIpMessage::RoutingActivationRequest(RoutingActivationRequest {
+                source_address: 0x0e00,
+                activation_type: ActivationType::CentralSecurity,
+                reserved: [0; 4],
+                reserved_oem: Some([1, 2, 3, 4]),
+            }),
+            DoIpMessage::RoutingActivationResponse(RoutingActivationResponse {
+                logical_address_tester: 0x0e00,
+                logical_address_of_doip_entity: 0x0077,
+                routing_activation_response_code:
+                    RoutingActivationResponseCode::RoutingSuccessfullyActivated,
+                reserved_oem: [0; 4],
+                oem_specific: None,
+            }),
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {}),
+            DoIpMessage::AliveCheckResponse(AliveCheckResponse {
+                source_address: 0x0e00,
+            }),
+            DoIpMessage::EntityStatusRequest(EntityStatusRequest {}),
+            DoIpMessage::EntityStatusResponse(EntityStatusResponse {
+                node_type: NodeType::Gateway,
+                max_open_sockets: 4,
+                cur_open_sockets: 1,
+                max_data_size: Some(4096),
+            }),
+            DoIpMessage::PowerModeRequest(PowerModeRequest {}),
+            DoIpMessage::PowerModeResponse(PowerModeResponse {
+                power_mode: PowerMode::Ready,
+            }),
+            DoIpMessage::DiagnosticMessage(DiagnosticMessage {
+                source_address: 0x0e00,
+                target_address: 0x0077,
+                user_data: owned(&[0x22, 0xf1, 0x90]),
+            }),
+            DoIpMessage::DiagnosticMessagePositiveAck(DiagnosticMessagePositiveAck {
+                source_address: 0x0077,
+                target_address: 0x0e00,
+                ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
+                previous_diagnostic_message_data: owned(&[0x22]),
+            }),
+            DoIpMessage::DiagnosticMessageNegativeAck(DiagnosticMessageNegativeAck {
+                source_address: 0x0077,
+                target_address: 0x0e00,
+                ack_code: DiagnosticMessageNegativeAckCode::UnknownTargetAddress,
+                previous_diagnostic_message_data: owned(&[]),
+            }),
+            DoIpMessage::Unknown {
+                payload_type: PayloadType::ReservedVm(0xf012),
+                bytes: owned(&[1, 2, 3]),
+            },
+        ];
+        for message in messages.iter() {
+            round_trip(message);
+        }
+    }
+
+    #[test]
+    fn identifiers_representation() {
+        let response = VehicleIdentificationResponse {
+            vin: Vin(*b"VF1YYYYYZTT000001"),
+            logical_address: 0x0077,
+            eid: Eid([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]),
+            gid: None,
+            further_action: FurtherActionRequired::NoFurtherActionRequired,
+            vin_gid_sync_status: None,
+        };
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["vin"], json!("VF1YYYYYZTT000001"));
+        assert_eq!(value["eid"], json!("001a2b3c4d5e"));
+        assert_eq!(value["gid"], json!(null));
+
+        let request = VehicleIdentificationRequestWithVin {
+            vin: Vin([0xff; 17]),
+        };
+        let value = serde_json::to_value(request).unwrap();
+        assert_eq!(value["vin"], json!("ff".repeat(17)));
+    }
+
+    #[test]
+    fn deserialize_from_json() {
+        let message: DoIpMessage = serde_json::from_value(json!({
+            "VehicleIdentificationResponse": {
+                "vin": "VF1YYYYYZTT000001",
+                "logical_address": 0x0077,
+                "eid": "001A2B3C4D5E",
+                "gid": "010203040506",
+                "further_action": "NoFurtherActionRequired",
+                "vin_gid_sync_status": null
+            }
+        }))
+        .unwrap();
+        let DoIpMessage::VehicleIdentificationResponse(response) = message else {
+            panic!("unexpected message {:?}", message);
+        };
+        assert_eq!(&response.vin.0, b"VF1YYYYYZTT000001");
+        assert_eq!(response.eid, Eid([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+        assert_eq!(response.gid, Some([1, 2, 3, 4, 5, 6]));
+
+        let wrong_eid = json!({ "eid": "001a2b3c4d" });
+        assert!(serde_json::from_value::<VehicleIdentificationRequestWithEid>(wrong_eid).is_err());
+        let wrong_vin = json!({ "vin": "VF1YYYYYZTT00000" });
+        assert!(serde_json::from_value::<VehicleIdentificationRequestWithVin>(wrong_vin).is_err());
+
+        let header: DoIpHeader = serde_json::from_value(json!({
+            "protocol_version": 2,
+            "inverse_protocol_version": 0xfd,
+            "payload_type": "DiagnosticMessage",
+            "payload_length": 7
+        }))
+        .unwrap();
+        assert_eq!(header, DoIpHeader::new(PayloadType::DiagnosticMessage, 7));
+        assert_eq!(
+            serde_json::to_value(ProtocolVersion::Iso13400_2019).unwrap(),
+            json!("Iso13400_2019")
+        );
+    }
+}
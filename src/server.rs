@@ -0,0 +1,960 @@
+use std::io::{self, ErrorKind, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::message::*;
+use crate::{write_message_versioned, DoIpError, DoIpHeader, DoIpMessage, HeaderValidator};
+use crate::{DoIpStream, LogicalAddress, Payload, VersionPolicy, DOIP_HEADER_LENGTH};
+
+/// Response of a [`DiagnosticHandler`] to a UDS request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticResponse {
+    /// The request is answered with this UDS response.
+    Response(Vec<u8>),
+    /// The request is not answered, eg. with the suppressPosRspMsgIndicationBit.
+    NoResponse,
+}
+
+/// UDS requests handler of a [`DoIpServer`]
+///
+/// The handler is called for each [`DiagnosticMessage`] received on a routing
+/// activated connection, and addressed to one of the
+/// [`ServerConfig::target_addresses`]. The request is first checked with
+/// [`DiagnosticHandler::accept()`], and positively acknowledged before
+/// [`DiagnosticHandler::handle()`] is called, so that a slow handler doesn't
+/// delay the acknowledge past A_DoIP_Diagnostic_Message (2s).
+///
+/// Each connection has its own clone of the handler given to the server, so
+/// that the connections are served concurrently, and the handler state, such
+/// as the active diagnostic session, is kept per tester.
+///
+/// Any clonable `FnMut(LogicalAddress, LogicalAddress, &[u8]) -> DiagnosticResponse`
+/// closure is a handler.
+pub trait DiagnosticHandler: Send {
+    /// Handles the UDS request `data`, sent by the tester `source_address` to
+    /// `target_address`.
+    fn handle(
+        &mut self,
+        source_address: LogicalAddress,
+        target_address: LogicalAddress,
+        data: &[u8],
+    ) -> DiagnosticResponse;
+
+    /// Checks the UDS request `data` before it is acknowledged, and returns
+    /// the negative acknowledge code if it is rejected.
+    ///
+    /// The default implementation accepts all the requests.
+    fn accept(
+        &mut self,
+        _source_address: LogicalAddress,
+        _target_address: LogicalAddress,
+        _data: &[u8],
+    ) -> Result<(), DiagnosticMessageNegativeAckCode> {
+        Ok(())
+    }
+}
+
+impl<F> DiagnosticHandler for F
+where
+    F: FnMut(LogicalAddress, LogicalAddress, &[u8]) -> DiagnosticResponse + Send,
+{
+    fn handle(
+        &mut self,
+        source_address: LogicalAddress,
+        target_address: LogicalAddress,
+        data: &[u8],
+    ) -> DiagnosticResponse {
+        self(source_address, target_address, data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Configuration of a [`DoIpServer`].
+///
+/// The default timings are the ones of ISO 13400-2.
+pub struct ServerConfig {
+    /// Logical address of the DoIP entity.
+    pub logical_address: LogicalAddress,
+    /// Logical addresses accepted as diagnostic message targets. If empty,
+    /// only [`ServerConfig::logical_address`] is accepted.
+    pub target_addresses: Vec<LogicalAddress>,
+    /// Tester logical addresses allowed to activate the routing. If `None`,
+    /// all testers are allowed.
+    pub source_addresses: Option<Vec<LogicalAddress>>,
+    /// Maximum number of routing activated connections. One more connection
+    /// is accepted to handle a new routing activation, and the others are
+    /// closed at once.
+    pub max_sockets: usize,
+    /// Maximum time between the connection and the routing activation, ie.
+    /// T_TCP_Initial_Inactivity (2s).
+    pub initial_inactivity: Duration,
+    /// Maximum time without any message on an activated connection, ie.
+    /// T_TCP_General_Inactivity (5min).
+    pub general_inactivity: Duration,
+    /// Maximum time to wait for an alive check response, ie.
+    /// T_TCP_Alive_Check (500ms).
+    pub alive_check_timeout: Duration,
+    /// Maximum accepted payload length.
+    pub max_payload_length: u32,
+    /// Protocol version of the sent messages, on each connection.
+    pub version_policy: VersionPolicy,
+    /// Whether the routing activation is denied on the connections in clear,
+    /// with [`RoutingActivationResponseCode::RoutingActivationDeniedEncryptedConnectionViaTLSRequired`],
+    /// so that the testers connect on the TLS listener instead.
+    pub require_tls: bool,
+}
+
+impl ServerConfig {
+    /// Creates a configuration with default values for the DoIP entity
+    /// `logical_address`.
+    pub fn new(logical_address: LogicalAddress) -> Self {
+        Self {
+            logical_address,
+            target_addresses: vec![],
+            source_addresses: None,
+            max_sockets: 4,
+            initial_inactivity: Duration::from_secs(2),
+            general_inactivity: Duration::from_secs(5 * 60),
+            alive_check_timeout: Duration::from_millis(500),
+            max_payload_length: HeaderValidator::default().max_payload_length,
+            version_policy: VersionPolicy::default(),
+            require_tls: false,
+        }
+    }
+
+    fn accepts_source(&self, source_address: LogicalAddress) -> bool {
+        match &self.source_addresses {
+            Some(addresses) => addresses.contains(&source_address),
+            None => true,
+        }
+    }
+
+    fn accepts_target(&self, target_address: LogicalAddress) -> bool {
+        if self.target_addresses.is_empty() {
+            target_address == self.logical_address
+        } else {
+            self.target_addresses.contains(&target_address)
+        }
+    }
+}
+
+/// Write half of a connection, with the protocol version of the sent
+/// messages.
+struct Writer {
+    stream: Box<dyn Write + Send>,
+    version: ProtocolVersion,
+}
+
+struct Connection {
+    id: u64,
+    tcp: TcpStream,
+    writer: Arc<Mutex<Writer>>,
+    source_address: Option<LogicalAddress>,
+    alive_responses: u64,
+}
+
+struct Shared {
+    config: ServerConfig,
+    validator: HeaderValidator,
+    connections: Mutex<Vec<Connection>>,
+    alive: Condvar,
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    /// Checks that the connections `ids` are still alive, and closes the ones
+    /// which are not. Returns the number of closed connections.
+    fn alive_check(&self, ids: &[u64]) -> usize {
+        let checked: Vec<(u64, u64, Arc<Mutex<Writer>>)> = {
+            let connections = self.connections.lock().unwrap();
+            connections
+                .iter()
+                .filter(|c| ids.contains(&c.id))
+                .map(|c| (c.id, c.alive_responses, c.writer.clone()))
+                .collect()
+        };
+        for (_, _, writer) in checked.iter() {
+            let _ = send(writer, &AliveCheckRequest {});
+        }
+
+        let deadline = Instant::now() + self.config.alive_check_timeout;
+        let mut connections = self.connections.lock().unwrap();
+        let is_dead = |connections: &Vec<Connection>, id: u64, responses: u64| {
+            connections
+                .iter()
+                .find(|c| c.id == id)
+                .is_some_and(|c| c.alive_responses == responses)
+        };
+        loop {
+            let waiting = checked
+                .iter()
+                .any(|(id, responses, _)| is_dead(&connections, *id, *responses));
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !waiting || remaining.is_zero() {
+                break;
+            }
+            connections = self.alive.wait_timeout(connections, remaining).unwrap().0;
+        }
+
+        let dead: Vec<u64> = checked
+            .iter()
+            .filter(|(id, responses, _)| is_dead(&connections, *id, *responses))
+            .map(|(id, _, _)| *id)
+            .collect();
+        connections.retain(|c| {
+            let alive = !dead.contains(&c.id);
+            if !alive {
+                let _ = c.tcp.shutdown(Shutdown::Both);
+            }
+            alive
+        });
+        dead.len()
+    }
+}
+
+/// DoIP entity server
+///
+/// The server accepts the TCP connections of external testers, and runs for
+/// each one the DoIP socket handling of ISO 13400-2 :
+/// - the routing must be activated before the initial inactivity timeout,
+/// - the routing activation is denied for unknown testers, or if the tester
+///   is already active on another connection,
+/// - when all the sockets are used, an alive check is performed on them, and
+///   the silent ones are closed to make room for the new one,
+/// - an activated connection is closed after the general inactivity timeout.
+///
+/// Incorrect DoIP headers are answered with the appropriate
+/// [`GenericDoIpHeaderNegativeAcknowledge`], and UDS requests are
+/// acknowledged and delegated to the [`DiagnosticHandler`].
+///
+/// With the `tls` feature, `DoIpServer::tls_listener()` accepts TLS
+/// connections as well, sharing the handler and the connections of the
+/// server.
+///
+/// Each connection is served in its own thread, with its own clone of the
+/// handler.
+///
+/// Example:
+/// ```no_run
+/// use doip_rw::{DiagnosticResponse, DoIpServer, ServerConfig};
+///
+/// let handler = |_source, _target, uds: &[u8]| {
+///     DiagnosticResponse::Response(vec![uds[0] + 0x40])
+/// };
+/// let server = DoIpServer::bind("127.0.0.1:13400", ServerConfig::new(0x0077), handler)?;
+/// server.run()?;
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+pub struct DoIpServer<H> {
+    listener: TcpListener,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
+    shared: Arc<Shared>,
+    /// Handler cloned for each connection.
+    handler: H,
+}
+
+impl<H: DiagnosticHandler + Clone + 'static> DoIpServer<H> {
+    /// Creates a server listening on `addr`.
+    pub fn bind<A: ToSocketAddrs>(
+        addr: A,
+        config: ServerConfig,
+        handler: H,
+    ) -> Result<Self, DoIpError> {
+        let listener = TcpListener::bind(addr)?;
+        let shared = Arc::new(Shared {
+            validator: HeaderValidator::new(config.max_payload_length),
+            config,
+            connections: Mutex::new(vec![]),
+            alive: Condvar::new(),
+            next_id: AtomicU64::new(0),
+        });
+        Ok(Self {
+            listener,
+            #[cfg(feature = "tls")]
+            tls: None,
+            shared,
+            handler,
+        })
+    }
+
+    /// Creates a server listening on `addr` for TLS connections, usually on
+    /// [`crate::TLS_PORT`], and sharing the handler and the connections of
+    /// this server.
+    ///
+    /// Both servers have to be run.
+    #[cfg(feature = "tls")]
+    pub fn tls_listener<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        tls_config: Arc<rustls::ServerConfig>,
+    ) -> Result<Self, DoIpError> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            tls: Some(tls_config),
+            shared: self.shared.clone(),
+            handler: self.handler.clone(),
+        })
+    }
+
+    /// Get the local address the server is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, DoIpError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accepts and serves connections, each one in its own thread.
+    ///
+    /// This function only returns if accepting a connection fails.
+    pub fn run(&self) -> Result<(), DoIpError> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            // Beyond the activated connections, and one more to handle a new
+            // routing activation, connections are closed at once.
+            if self.shared.connections.lock().unwrap().len() > self.shared.config.max_sockets {
+                let _ = stream.shutdown(Shutdown::Both);
+                continue;
+            }
+            #[cfg(feature = "tls")]
+            if let Some(tls_config) = &self.tls {
+                let tls_config = tls_config.clone();
+                let shared = self.shared.clone();
+                let handler = self.handler.clone();
+                thread::spawn(move || {
+                    // The handshake is part of the initial inactivity
+                    stream.set_read_timeout(Some(shared.config.initial_inactivity))?;
+                    let stream = crate::TlsStream::server(stream, tls_config)?;
+                    Session::new(stream, true, shared, handler)?.run();
+                    Ok::<(), DoIpError>(())
+                });
+                continue;
+            }
+            let session = Session::new(stream, false, self.shared.clone(), self.handler.clone())?;
+            thread::spawn(move || session.run());
+        }
+    }
+}
+
+/// Maximum number of payload bytes read at once, so that the payload buffer
+/// grows as the bytes are received.
+const READ_CHUNK_SIZE: usize = 4096;
+
+struct Session<H, S> {
+    id: u64,
+    stream: S,
+    secure: bool,
+    writer: Arc<Mutex<Writer>>,
+    shared: Arc<Shared>,
+    handler: H,
+    source_address: Option<LogicalAddress>,
+    version: VersionPolicy,
+}
+
+/// Whether the connection should be kept open after handling a message.
+enum Control {
+    Continue,
+    Close,
+}
+
+impl<H: DiagnosticHandler, S: DoIpStream + Send + 'static> Session<H, S> {
+    fn new(stream: S, secure: bool, shared: Arc<Shared>, handler: H) -> Result<Self, DoIpError> {
+        stream.tcp_stream().set_nodelay(true)?;
+        let version = shared.config.version_policy;
+        let writer = Arc::new(Mutex::new(Writer {
+            stream: Box::new(stream.try_clone()?),
+            version: version.version(),
+        }));
+        let id = shared.next_id.fetch_add(1, Ordering::Relaxed);
+        shared.connections.lock().unwrap().push(Connection {
+            id,
+            tcp: stream.tcp_stream().try_clone()?,
+            writer: writer.clone(),
+            source_address: None,
+            alive_responses: 0,
+        });
+        Ok(Self {
+            id,
+            stream,
+            secure,
+            writer,
+            shared,
+            handler,
+            source_address: None,
+            version,
+        })
+    }
+
+    fn run(mut self) {
+        let _ = self.serve();
+        let _ = self.stream.tcp_stream().shutdown(Shutdown::Both);
+        let mut connections = self.shared.connections.lock().unwrap();
+        connections.retain(|c| c.id != self.id);
+    }
+
+    fn serve(&mut self) -> Result<(), DoIpError> {
+        let shared = self.shared.clone();
+        let config = &shared.config;
+        let initial_deadline = Instant::now() + config.initial_inactivity;
+        let mut payload = vec![];
+        loop {
+            let deadline = match self.source_address {
+                None => initial_deadline,
+                Some(_) => Instant::now() + config.general_inactivity,
+            };
+            let mut header_bytes = [0u8; DOIP_HEADER_LENGTH];
+            if !self.read_exact_before(&mut header_bytes, deadline)? {
+                return Ok(());
+            }
+            let header = DoIpHeader::from_bytes(&header_bytes);
+
+            // The payload has to be received before the same deadline.
+            let payload_length = header.payload_length as usize;
+            if let Err(e) = self.shared.validator.validate(&header) {
+                if let Some(nack) = e.header_nack() {
+                    self.send(&nack)?;
+                }
+                match e {
+                    DoIpError::UnknownPayloadType(_) | DoIpError::MessageTooLarge { .. } => {
+                        if !self.skip_before(payload_length, deadline)? {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                    e => return Err(e),
+                }
+            }
+            self.version.learn(&header);
+            self.writer.lock().unwrap().version = self.version.version();
+
+            // The buffer grows as the payload is received, rather than being
+            // sized from the header.
+            payload.clear();
+            while payload.len() < payload_length {
+                let start = payload.len();
+                payload.resize(payload_length.min(start + READ_CHUNK_SIZE), 0);
+                if !self.read_exact_before(&mut payload[start..], deadline)? {
+                    return Ok(());
+                }
+            }
+            let control = match DoIpMessage::read(
+                &mut &payload[..],
+                header.payload_type,
+                payload.len(),
+            ) {
+                Ok(message) => self.handle(message)?,
+                Err(DoIpError::UnknownActivationType(_)) => {
+                    let source_address = u16::from_be_bytes([payload[0], payload[1]]);
+                    self.routing_activation_response(
+                        source_address,
+                        RoutingActivationResponseCode::RoutingActivationDeniedUnsupportedRoutingActivationType,
+                    )?
+                }
+                Err(e) => {
+                    // The payload does not match its payload type, the
+                    // messages are out of sync.
+                    let nack = e
+                        .header_nack()
+                        .unwrap_or(GenericDoIpHeaderNegativeAcknowledge {
+                            nack_code: NegativeAckCode::InvalidPayloadLength,
+                        });
+                    self.send(&nack)?;
+                    Control::Close
+                }
+            };
+            if let Control::Close = control {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads exactly `buf.len()` bytes before `deadline`, or returns `false`
+    /// if the deadline is reached.
+    fn read_exact_before(
+        &mut self,
+        mut buf: &mut [u8],
+        deadline: Instant,
+    ) -> Result<bool, DoIpError> {
+        while !buf.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.stream.read(buf) {
+                Ok(0) => return Err(io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(false)
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads and discards `len` bytes before `deadline`, or returns `false`
+    /// if the deadline is reached.
+    fn skip_before(&mut self, mut len: usize, deadline: Instant) -> Result<bool, DoIpError> {
+        let mut buf = [0u8; 256];
+        while len > 0 {
+            let chunk = len.min(buf.len());
+            if !self.read_exact_before(&mut buf[..chunk], deadline)? {
+                return Ok(false);
+            }
+            len -= chunk;
+        }
+        Ok(true)
+    }
+
+    fn handle(&mut self, message: DoIpMessage) -> Result<Control, DoIpError> {
+        match message {
+            DoIpMessage::RoutingActivationRequest(request) => self.routing_activation(request),
+            DoIpMessage::DiagnosticMessage(request) => self.diagnostic_message(request),
+            DoIpMessage::AliveCheckResponse(_) => {
+                let mut connections = self.shared.connections.lock().unwrap();
+                if let Some(c) = connections.iter_mut().find(|c| c.id == self.id) {
+                    c.alive_responses += 1;
+                }
+                self.shared.alive.notify_all();
+                Ok(Control::Continue)
+            }
+            _ => Ok(Control::Continue),
+        }
+    }
+
+    fn routing_activation(
+        &mut self,
+        request: RoutingActivationRequest,
+    ) -> Result<Control, DoIpError> {
+        use RoutingActivationResponseCode::*;
+        let source_address = request.source_address;
+        let code = if !self.shared.config.accepts_source(source_address) {
+            RoutingActivationDeniedUnknownSourceAddress
+        } else if self.shared.config.require_tls && !self.secure {
+            RoutingActivationDeniedEncryptedConnectionViaTLSRequired
+        } else if let Some(registered) = self.source_address {
+            if registered == source_address {
+                RoutingSuccessfullyActivated
+            } else {
+                RoutingActivationDeniedSourceAddressAlreadyActivated
+            }
+        } else {
+            self.register(source_address)
+        };
+        self.routing_activation_response(source_address, code)
+    }
+
+    /// Registers `source_address` on this connection, closing the silent
+    /// connections if needed.
+    ///
+    /// The alive checks are done without holding the connections lock, so the
+    /// registration is checked again under the lock before being done.
+    fn register(&mut self, source_address: LogicalAddress) -> RoutingActivationResponseCode {
+        use RoutingActivationResponseCode::*;
+        let shared = &self.shared;
+        let same_source: Vec<u64> = activated(shared, |c| c.source_address == Some(source_address));
+        if !same_source.is_empty() && shared.alive_check(&same_source) < same_source.len() {
+            return RoutingActivationDeniedSourceAddressAlreadyRegistred;
+        }
+        let all: Vec<u64> = activated(shared, |c| c.id != self.id);
+        if all.len() >= shared.config.max_sockets && shared.alive_check(&all) == 0 {
+            return RoutingActivationDeniedAllTcpSocketsRegisteredAndActive;
+        }
+        // Other connections may have been activated during the alive checks.
+        let mut connections = shared.connections.lock().unwrap();
+        let others = || {
+            connections
+                .iter()
+                .filter(|c| c.id != self.id && c.source_address.is_some())
+        };
+        if others().any(|c| c.source_address == Some(source_address)) {
+            return RoutingActivationDeniedSourceAddressAlreadyRegistred;
+        }
+        if others().count() >= shared.config.max_sockets {
+            return RoutingActivationDeniedAllTcpSocketsRegisteredAndActive;
+        }
+        match connections.iter_mut().find(|c| c.id == self.id) {
+            Some(c) => c.source_address = Some(source_address),
+            None => return RoutingActivationDeniedAllTcpSocketsRegisteredAndActive,
+        }
+        self.source_address = Some(source_address);
+        RoutingSuccessfullyActivated
+    }
+
+    fn routing_activation_response(
+        &mut self,
+        source_address: LogicalAddress,
+        code: RoutingActivationResponseCode,
+    ) -> Result<Control, DoIpError> {
+        let response = RoutingActivationResponse::builder(
+            source_address,
+            self.shared.config.logical_address,
+            code,
+        )
+        .build();
+        self.send(&response)?;
+        match code {
+            RoutingActivationResponseCode::RoutingSuccessfullyActivated => Ok(Control::Continue),
+            _ => Ok(Control::Close),
+        }
+    }
+
+    fn diagnostic_message(&mut self, request: DiagnosticMessage) -> Result<Control, DoIpError> {
+        let source_address = request.source_address;
+        let target_address = request.target_address;
+        let nack =
+            |ack_code| DiagnosticMessageNegativeAck::new(target_address, source_address, ack_code);
+        if self.source_address != Some(source_address) {
+            self.send(&nack(
+                DiagnosticMessageNegativeAckCode::InvalidSourceAddress,
+            ))?;
+            return Ok(Control::Close);
+        }
+        if !self.shared.config.accepts_target(target_address) {
+            self.send(&nack(
+                DiagnosticMessageNegativeAckCode::UnknownTargetAddress,
+            ))?;
+            return Ok(Control::Continue);
+        }
+
+        let data = request.user_data.get_ref();
+        if let Err(ack_code) = self.handler.accept(source_address, target_address, data) {
+            self.send(&nack(ack_code))?;
+            return Ok(Control::Continue);
+        }
+        self.send(&DiagnosticMessagePositiveAck::new(
+            target_address,
+            source_address,
+        ))?;
+        match self.handler.handle(source_address, target_address, data) {
+            DiagnosticResponse::Response(data) => {
+                let response = DiagnosticMessage::new(target_address, source_address, data);
+                self.send(&response)?;
+            }
+            DiagnosticResponse::NoResponse => {}
+        }
+        Ok(Control::Continue)
+    }
+
+    fn send<P: Payload>(&self, payload: &P) -> Result<(), DoIpError> {
+        send(&self.writer, payload)
+    }
+}
+
+fn activated<F>(shared: &Shared, filter: F) -> Vec<u64>
+where
+    F: Fn(&Connection) -> bool,
+{
+    let connections = shared.connections.lock().unwrap();
+    connections
+        .iter()
+        .filter(|c| c.source_address.is_some() && filter(c))
+        .map(|c| c.id)
+        .collect()
+}
+
+fn send<P: Payload>(writer: &Mutex<Writer>, payload: &P) -> Result<(), DoIpError> {
+    let mut writer = writer.lock().unwrap();
+    let mut buf = Vec::with_capacity(DOIP_HEADER_LENGTH + payload.length());
+    write_message_versioned(writer.version, payload, &mut buf)?;
+    writer.stream.write_all(&buf)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{read_header, read_message, read_payload, write_message, DoIpClient};
+    use std::io::Read;
+
+    const ENTITY: LogicalAddress = 0x0077;
+
+    /// Echoes the requests, with the target address, and rejects the
+    /// ECUReset requests.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl DiagnosticHandler for Echo {
+        fn handle(
+            &mut self,
+            _source: LogicalAddress,
+            target: LogicalAddress,
+            uds: &[u8],
+        ) -> DiagnosticResponse {
+            match uds {
+                [0x3e, 0x80] => DiagnosticResponse::NoResponse,
+                // RoutineControl, taking some time
+                [0x31, ..] => {
+                    thread::sleep(Duration::from_millis(300));
+                    DiagnosticResponse::Response(vec![0x71])
+                }
+                [sid, rest @ ..] => {
+                    let mut response = vec![sid + 0x40];
+                    response.extend_from_slice(rest);
+                    response.extend_from_slice(&u16::to_be_bytes(target));
+                    DiagnosticResponse::Response(response)
+                }
+                [] => DiagnosticResponse::Response(vec![]),
+            }
+        }
+
+        fn accept(
+            &mut self,
+            _source: LogicalAddress,
+            _target: LogicalAddress,
+            uds: &[u8],
+        ) -> Result<(), DiagnosticMessageNegativeAckCode> {
+            match uds {
+                [0x11, ..] => Err(DiagnosticMessageNegativeAckCode::OutOfMemory),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn server(config: ServerConfig) -> SocketAddr {
+        let server = DoIpServer::bind("127.0.0.1:0", config, Echo).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    fn activate(addr: SocketAddr, source_address: LogicalAddress) -> Result<TcpStream, DoIpError> {
+        let mut tcp = TcpStream::connect(addr)?;
+        let request = RoutingActivationRequest {
+            source_address,
+            activation_type: ActivationType::Default,
+            reserved: [0; 4],
+            reserved_oem: None,
+        };
+        write_message(&request, &mut tcp)?;
+        let response: RoutingActivationResponse = read_message(&mut tcp)?;
+        match response.routing_activation_response_code {
+            RoutingActivationResponseCode::RoutingSuccessfullyActivated => Ok(tcp),
+            code => Err(DoIpError::RoutingActivationDenied(code)),
+        }
+    }
+
+    fn answer_alive_check(mut tcp: TcpStream, source_address: LogicalAddress) {
+        thread::spawn(move || {
+            while read_message::<_, AliveCheckRequest>(&mut tcp).is_ok() {
+                let response = AliveCheckResponse { source_address };
+                if write_message(&response, &mut tcp).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn diagnostic() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.target_addresses = vec![ENTITY, 0x0078];
+        let addr = server(config);
+        let mut client = DoIpClient::connect(addr, 0x0e00).unwrap();
+        client.activate_routing(ActivationType::Default).unwrap();
+
+        let response = client.send_diagnostic(0x0078, &[0x22, 0xf1, 0x90]).unwrap();
+        assert_eq!(response, vec![0x62, 0xf1, 0x90, 0x00, 0x78]);
+        let err = client
+            .send_diagnostic(0x0079, &[0x22, 0xf1, 0x90])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DoIpError::DiagnosticMessageNegativeAck(
+                DiagnosticMessageNegativeAckCode::UnknownTargetAddress
+            )
+        ));
+        let err = client.send_diagnostic(ENTITY, &[0x11, 0x01]).unwrap_err();
+        assert!(matches!(
+            err,
+            DoIpError::DiagnosticMessageNegativeAck(DiagnosticMessageNegativeAckCode::OutOfMemory)
+        ));
+    }
+
+    #[test]
+    fn handler_per_connection() {
+        let mut count = 0u8;
+        let handler = move |_source, _target, _uds: &[u8]| {
+            count += 1;
+            DiagnosticResponse::Response(vec![0x7e, count])
+        };
+        let server = DoIpServer::bind("127.0.0.1:0", ServerConfig::new(ENTITY), handler).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        for tester in [0x0e00, 0x0e01] {
+            let mut client = DoIpClient::connect(addr, tester).unwrap();
+            client.activate_routing(ActivationType::Default).unwrap();
+            for expected in 1..=2 {
+                let response = client.send_diagnostic(ENTITY, &[0x3e, 0x00]).unwrap();
+                assert_eq!(response, vec![0x7e, expected]);
+            }
+        }
+    }
+
+    #[test]
+    fn ack_before_handling() {
+        let addr = server(ServerConfig::new(ENTITY));
+        let mut tcp = activate(addr, 0x0e00).unwrap();
+        let start = Instant::now();
+        write_message(
+            &DiagnosticMessage::new(0x0e00, ENTITY, vec![0x31, 0x01]),
+            &mut tcp,
+        )
+        .unwrap();
+        let _: DiagnosticMessagePositiveAck = read_message(&mut tcp).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(200));
+        let response: DiagnosticMessage = read_message(&mut tcp).unwrap();
+        assert_eq!(response.user_data.get_ref(), &[0x71]);
+    }
+
+    #[test]
+    fn diagnostic_without_routing() {
+        let addr = server(ServerConfig::new(ENTITY));
+        let mut client = DoIpClient::connect(addr, 0x0e00).unwrap();
+        let err = client.send_diagnostic(ENTITY, &[0x3e, 0x00]).unwrap_err();
+        assert!(matches!(
+            err,
+            DoIpError::DiagnosticMessageNegativeAck(
+                DiagnosticMessageNegativeAckCode::InvalidSourceAddress
+            )
+        ));
+    }
+
+    #[test]
+    fn routing_activation_unknown_source() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.source_addresses = Some(vec![0x0e00]);
+        let addr = server(config);
+        assert!(activate(addr, 0x0e00).is_ok());
+        assert!(matches!(
+            activate(addr, 0x0e01),
+            Err(DoIpError::RoutingActivationDenied(
+                RoutingActivationResponseCode::RoutingActivationDeniedUnknownSourceAddress
+            ))
+        ));
+    }
+
+    #[test]
+    fn header_nack() {
+        let addr = server(ServerConfig::new(ENTITY));
+        let mut tcp = TcpStream::connect(addr).unwrap();
+        tcp.write_all(&[0x02, 0xfd, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x01, 0xaa])
+            .unwrap();
+        let nack: GenericDoIpHeaderNegativeAcknowledge = read_message(&mut tcp).unwrap();
+        assert_eq!(nack.nack_code, NegativeAckCode::UnknownPayloadType);
+        tcp.write_all(&[0x02, 0xfe, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00])
+            .unwrap();
+        let nack: GenericDoIpHeaderNegativeAcknowledge = read_message(&mut tcp).unwrap();
+        assert_eq!(nack.nack_code, NegativeAckCode::IncorrectPatternFormat);
+        assert_eq!(tcp.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn invalid_payload_length() {
+        let addr = server(ServerConfig::new(ENTITY));
+        let mut tcp = TcpStream::connect(addr).unwrap();
+        // Routing activation request, with a 3 bytes payload
+        tcp.write_all(&[
+            0x02, 0xfd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0x0e, 0x00, 0x00,
+        ])
+        .unwrap();
+        let nack: GenericDoIpHeaderNegativeAcknowledge = read_message(&mut tcp).unwrap();
+        assert_eq!(nack.nack_code, NegativeAckCode::InvalidPayloadLength);
+        assert_eq!(tcp.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn version_echo() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.version_policy = VersionPolicy::Echo(ProtocolVersion::DoIpIso);
+        let addr = server(config);
+        let mut tcp = TcpStream::connect(addr).unwrap();
+        let request = RoutingActivationRequest {
+            source_address: 0x0e00,
+            activation_type: ActivationType::Default,
+            reserved: [0; 4],
+            reserved_oem: None,
+        };
+        write_message_versioned(ProtocolVersion::Iso13400_2019, &request, &mut tcp).unwrap();
+        let header = read_header(&mut tcp).unwrap();
+        assert_eq!(
+            header.protocol_version,
+            ProtocolVersion::Iso13400_2019 as u8
+        );
+        let response: RoutingActivationResponse =
+            read_payload(&mut tcp, header.payload_length as usize).unwrap();
+        assert_eq!(
+            response.routing_activation_response_code,
+            RoutingActivationResponseCode::RoutingSuccessfullyActivated
+        );
+    }
+
+    #[test]
+    fn initial_inactivity() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.initial_inactivity = Duration::from_millis(100);
+        let addr = server(config);
+        let mut tcp = TcpStream::connect(addr).unwrap();
+        tcp.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        assert_eq!(tcp.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn initial_inactivity_during_payload() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.initial_inactivity = Duration::from_millis(100);
+        let addr = server(config);
+        let mut tcp = TcpStream::connect(addr).unwrap();
+        tcp.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        // Diagnostic message header announcing 1 MiB, and a few bytes of it
+        tcp.write_all(&[0x02, 0xfd, 0x80, 0x01, 0x00, 0x10, 0x00, 0x00, 0x0e, 0x00])
+            .unwrap();
+        assert_eq!(tcp.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn connections_limit() {
+        let mut config = ServerConfig::new(ENTITY);
+        config.max_sockets = 1;
+        let addr = server(config);
+        let _first = activate(addr, 0x0e00).unwrap();
+        let _second = TcpStream::connect(addr).unwrap();
+        let mut third = TcpStream::connect(addr).unwrap();
+        third
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        assert_eq!(third.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn socket_exhaustion() {
+        use RoutingActivationResponseCode::*;
+        let mut config = ServerConfig::new(ENTITY);
+        config.max_sockets = 1;
+        config.alive_check_timeout = Duration::from_millis(100);
+        let addr = server(config);
+
+        // A silent tester is replaced by the new one.
+        let _silent = activate(addr, 0x0e00).unwrap();
+        let alive = activate(addr, 0x0e01).unwrap();
+
+        // An alive tester is kept.
+        answer_alive_check(alive, 0x0e01);
+        assert!(matches!(
+            activate(addr, 0x0e02),
+            Err(DoIpError::RoutingActivationDenied(
+                RoutingActivationDeniedAllTcpSocketsRegisteredAndActive
+            ))
+        ));
+        assert!(matches!(
+            activate(addr, 0x0e01),
+            Err(DoIpError::RoutingActivationDenied(
+                RoutingActivationDeniedSourceAddressAlreadyRegistred
+            ))
+        ));
+    }
+}
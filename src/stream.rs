@@ -0,0 +1,34 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// TCP connection carrying DoIP messages
+///
+/// The DoIP messages are exchanged either in clear over a [`TcpStream`], or
+/// over a TLS connection with the `tls` feature.
+pub trait DoIpStream: Read + Write + Sized {
+    /// Sets the read timeout of the connection, as
+    /// [`TcpStream::set_read_timeout()`].
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Creates another handle to the same connection, for example to write
+    /// from one thread while another thread reads.
+    fn try_clone(&self) -> io::Result<Self>;
+
+    /// Get the underlying TCP stream.
+    fn tcp_stream(&self) -> &TcpStream;
+}
+
+impl DoIpStream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn tcp_stream(&self) -> &TcpStream {
+        self
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, Connection, ServerConfig, ServerConnection};
+
+use crate::{DoIpError, DoIpStream};
+
+/// TCP port of the DoIP entities for TLS connections (TCP_DATA_TLS).
+pub const TLS_PORT: u16 = 3496;
+
+/// TLS connection carrying DoIP messages
+///
+/// The stream can be cloned, the clones sharing the same TLS session, so
+/// that one thread can write messages while another one is blocked reading.
+/// A reader only waits for the TLS records without holding the session, and
+/// never delays the writers.
+///
+/// Example:
+/// ```no_run
+/// use std::sync::Arc;
+/// use doip_rw::{DoIpClient, TlsStream, TLS_PORT};
+///
+/// # fn config() -> Arc<rustls::ClientConfig> { unimplemented!() }
+/// let server_name = "ecu.vehicle".try_into().unwrap();
+/// let stream = TlsStream::connect(("192.168.0.10", TLS_PORT), server_name, config())?;
+/// let mut client = DoIpClient::from_stream(stream, 0x0e00)?;
+/// # Ok::<(), doip_rw::DoIpError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsStream {
+    session: Arc<Mutex<Session>>,
+    tcp: Arc<TcpStream>,
+}
+
+#[derive(Debug)]
+struct Session {
+    conn: Connection,
+    tcp: TcpStream,
+}
+
+impl TlsStream {
+    /// Connects to a DoIP entity, and performs the TLS handshake as a client.
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        server_name: ServerName<'static>,
+        config: Arc<ClientConfig>,
+    ) -> Result<Self, DoIpError> {
+        let tcp = TcpStream::connect(addr)?;
+        Self::client(tcp, server_name, config)
+    }
+
+    /// Performs the TLS handshake as a client on an already connected stream.
+    pub fn client(
+        tcp: TcpStream,
+        server_name: ServerName<'static>,
+        config: Arc<ClientConfig>,
+    ) -> Result<Self, DoIpError> {
+        let conn = ClientConnection::new(config, server_name).map_err(io::Error::other)?;
+        Self::handshake(tcp, conn.into())
+    }
+
+    /// Performs the TLS handshake as a server on an accepted stream.
+    pub fn server(tcp: TcpStream, config: Arc<ServerConfig>) -> Result<Self, DoIpError> {
+        let conn = ServerConnection::new(config).map_err(io::Error::other)?;
+        Self::handshake(tcp, conn.into())
+    }
+
+    fn handshake(mut tcp: TcpStream, mut conn: Connection) -> Result<Self, DoIpError> {
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp)?;
+        }
+        Ok(Self {
+            tcp: Arc::new(tcp.try_clone()?),
+            session: Arc::new(Mutex::new(Session { conn, tcp })),
+        })
+    }
+
+    /// Sends the TLS close notification to the peer.
+    pub fn close(&self) -> io::Result<()> {
+        let mut session = self.session.lock().unwrap();
+        session.conn.send_close_notify();
+        session.flush_tls()
+    }
+}
+
+impl Session {
+    fn flush_tls(&mut self) -> io::Result<()> {
+        while self.conn.wants_write() {
+            self.conn.write_tls(&mut self.tcp)?;
+        }
+        Ok(())
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            {
+                let mut session = self.session.lock().unwrap();
+                match session.conn.reader().read(buf) {
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+                    result => return result,
+                }
+            }
+
+            // Wait for the next TLS records, until the read timeout
+            self.tcp.peek(&mut [0u8])?;
+
+            let mut session = self.session.lock().unwrap();
+            let Session { conn, tcp } = &mut *session;
+            conn.read_tls(tcp)?;
+            conn.process_new_packets()
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            session.flush_tls()?;
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut session = self.session.lock().unwrap();
+        let len = session.conn.writer().write(buf)?;
+        session.flush_tls()?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut session = self.session.lock().unwrap();
+        session.conn.writer().flush()?;
+        session.flush_tls()
+    }
+}
+
+impl DoIpStream for TlsStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.tcp.set_read_timeout(timeout)
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.clone())
+    }
+
+    fn tcp_stream(&self) -> &TcpStream {
+        &self.tcp
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::*;
+    use crate::{DiagnosticResponse, DoIpClient, DoIpServer, ServerConfig as DoIpServerConfig};
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::RootCertStore;
+    use std::net::SocketAddr;
+    use std::thread;
+
+    const TESTER: u16 = 0x0e00;
+
+    /// Returns the server and client TLS configurations, with a self-signed
+    /// certificate for "localhost".
+    fn tls_configs() -> (Arc<ServerConfig>, Arc<ClientConfig>) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let cert: CertificateDer = certified.cert.der().clone();
+        let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+
+        let server = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.clone()], PrivateKeyDer::Pkcs8(key))
+            .unwrap();
+        let mut roots = RootCertStore::empty();
+        roots.add(cert).unwrap();
+        let client = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        (Arc::new(server), Arc::new(client))
+    }
+
+    /// Starts a server requiring TLS, and returns its clear and TLS addresses.
+    fn servers(
+        config: DoIpServerConfig,
+        tls_config: Arc<ServerConfig>,
+    ) -> (SocketAddr, SocketAddr) {
+        let handler =
+            |_source, _target, uds: &[u8]| DiagnosticResponse::Response(vec![uds[0] + 0x40]);
+        let server = DoIpServer::bind("127.0.0.1:0", config, handler).unwrap();
+        let tls_server = server.tls_listener("127.0.0.1:0", tls_config).unwrap();
+        let addrs = (
+            server.local_addr().unwrap(),
+            tls_server.local_addr().unwrap(),
+        );
+        thread::spawn(move || server.run());
+        thread::spawn(move || tls_server.run());
+        addrs
+    }
+
+    fn connect(addr: SocketAddr, config: Arc<ClientConfig>) -> DoIpClient<TlsStream> {
+        let name = ServerName::try_from("localhost").unwrap();
+        let stream = TlsStream::connect(addr, name, config).unwrap();
+        DoIpClient::from_stream(stream, TESTER).unwrap()
+    }
+
+    #[test]
+    fn fallback_to_tls() {
+        let (server_tls, client_tls) = tls_configs();
+        let mut config = DoIpServerConfig::new(0x0077);
+        config.require_tls = true;
+        let (addr, tls_addr) = servers(config, server_tls);
+
+        let mut client = DoIpClient::connect(addr, TESTER).unwrap();
+        let e = client
+            .activate_routing(ActivationType::Default)
+            .unwrap_err();
+        assert!(e.requires_tls(), "{:?}", e);
+
+        let mut client = connect(tls_addr, client_tls);
+        client.activate_routing(ActivationType::Default).unwrap();
+        let response = client.send_diagnostic(0x0077, &[0x3e, 0x00]).unwrap();
+        assert_eq!(response, vec![0x7e]);
+    }
+
+    #[test]
+    fn alive_check() {
+        let (server_tls, client_tls) = tls_configs();
+        let mut config = DoIpServerConfig::new(0x0077);
+        config.max_sockets = 1;
+        config.alive_check_timeout = Duration::from_millis(100);
+        let (_, tls_addr) = servers(config, server_tls);
+
+        // The silent connection is closed, the server writing the alive check
+        // while its session is blocked reading.
+        let mut silent = connect(tls_addr, client_tls.clone());
+        silent.activate_routing(ActivationType::Default).unwrap();
+        let mut client = connect(tls_addr, client_tls);
+        client.activate_routing(ActivationType::Default).unwrap();
+        let response = client.send_diagnostic(0x0077, &[0x10, 0x01]).unwrap();
+        assert_eq!(response, vec![0x50]);
+    }
+
+    #[test]
+    fn untrusted_certificate() {
+        let (server_tls, _) = tls_configs();
+        let (_, client_tls) = tls_configs();
+        let (_, tls_addr) = servers(DoIpServerConfig::new(0x0077), server_tls);
+        let name = ServerName::try_from("localhost").unwrap();
+        assert!(TlsStream::connect(tls_addr, name, client_tls).is_err());
+    }
+}
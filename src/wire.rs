@@ -1,5 +1,6 @@
 pub mod alive_check;
 pub mod diagnostic_message;
+pub mod doip_message;
 pub mod entity_status;
 pub mod generic_header_nack;
 pub mod header;
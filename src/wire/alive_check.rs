@@ -1,8 +1,9 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder};
 
 use super::super::proto::alive_check::*;
-use crate::{DoIpError, Payload, PayloadType};
+use super::payload::payload_buffer;
+use crate::{BorrowedPayload, DoIpError, Payload, PayloadType};
 
 impl Payload for AliveCheckRequest {
     fn length(&self) -> usize {
@@ -29,11 +30,22 @@ impl Payload for AliveCheckRequest {
     fn write<T: Write>(&self, _writer: &mut T) -> Result<(), DoIpError> {
         Ok(())
     }
+
+    fn encode_into(&self, _buf: &mut [u8]) -> Result<usize, DoIpError> {
+        Ok(0)
+    }
+}
+
+impl BorrowedPayload<'_> for AliveCheckRequest {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
 impl Payload for AliveCheckResponse {
     fn length(&self) -> usize {
-        size::ALRSP_DEFAULT_SIZE
+        AliveCheckResponse::LENGTH
     }
 
     fn payload_type() -> PayloadType {
@@ -52,10 +64,10 @@ impl Payload for AliveCheckResponse {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         use DoIpError::*;
-        if payload_length != size::ALRSP_DEFAULT_SIZE {
+        if payload_length != AliveCheckResponse::LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::ALRSP_DEFAULT_SIZE as u32,
+                expected: AliveCheckResponse::LENGTH as u32,
             });
         }
         self.source_address = reader.read_u16::<BigEndian>()?;
@@ -66,15 +78,25 @@ impl Payload for AliveCheckResponse {
         writer.write_u16::<BigEndian>(self.source_address)?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, Self::LENGTH)?;
+        BigEndian::write_u16(&mut buf[0..2], self.source_address);
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for AliveCheckResponse {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
-mod size {
+pub(crate) mod size {
     use super::AliveCheckResponse;
-    use crate::LogicalAddress;
-    use std::mem::size_of;
 
     pub const ALRSP_ZEROED: AliveCheckResponse = AliveCheckResponse { source_address: 0 };
-    pub const ALRSP_DEFAULT_SIZE: usize = size_of::<LogicalAddress>();
 }
 
 fn assert_empty_payload(payload_length: usize) -> Result<(), DoIpError> {
@@ -1,6 +1,7 @@
+use super::payload::payload_buffer;
+use crate::io::{read_to_vec, Read, ReadBytesExt, Write, WriteBytesExt};
 use crate::{proto::payload::BorrowedPayload, DoIpError, LogicalAddress, Payload, PayloadType};
-use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use byteorder::{BigEndian, ByteOrder};
 
 use crate::proto::diagnostic_message::*;
 
@@ -16,6 +17,11 @@ fn get_addrs(buffer: &[u8]) -> (LogicalAddress, LogicalAddress) {
     (source_address, target_address)
 }
 
+fn put_addrs(buffer: &mut [u8], source_address: LogicalAddress, target_address: LogicalAddress) {
+    BigEndian::write_u16(&mut buffer[0..2], source_address);
+    BigEndian::write_u16(&mut buffer[2..4], target_address);
+}
+
 fn write_addrs<T: Write>(
     writer: &mut T,
     source_address: &LogicalAddress,
@@ -26,9 +32,22 @@ fn write_addrs<T: Write>(
     Ok(())
 }
 
+fn read_user_data<T: Read>(
+    reader: &mut T,
+    user_data: &mut UdsBuffer,
+    user_data_len: usize,
+) -> Result<(), DoIpError> {
+    let buffer = match user_data {
+        UdsBuffer::Borrowed(_) => Err(DoIpError::BorrowedBuffer(user_data_len)),
+        UdsBuffer::Owned(ref mut buf) => Ok(buf),
+    }?;
+    read_to_vec(reader, buffer, user_data_len)?;
+    Ok(())
+}
+
 impl<'a> Payload for DiagnosticMessage<'a> {
     fn length(&self) -> usize {
-        size::DIAGREQ_DEFAULT_SIZE + self.user_data.get_ref().len()
+        DiagnosticMessage::MIN_LENGTH + self.user_data.get_ref().len()
     }
 
     fn payload_type() -> PayloadType {
@@ -43,7 +62,7 @@ impl<'a> Payload for DiagnosticMessage<'a> {
         /*let mut me = Self {
             source_address: 0,
             target_address: 0,
-            user_data: UdsBuffer::Owned(vec![]),
+            user_data: UdsBuffer::Owned(Vec::new()),
         };*/
         me.read_replace(reader, payload_length)?;
         Ok(me)
@@ -55,21 +74,15 @@ impl<'a> Payload for DiagnosticMessage<'a> {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         use DoIpError::*;
-        if payload_length < size::DIAGREQ_DEFAULT_SIZE {
+        if payload_length < DiagnosticMessage::MIN_LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::DIAGREQ_DEFAULT_SIZE as u32,
+                expected: DiagnosticMessage::MIN_LENGTH as u32,
             });
         }
         (self.source_address, self.target_address) = read_addrs(reader)?;
         let user_data_len = payload_length - 4; // 4 == source + target address
-        let buffer = match self.user_data {
-            UdsBuffer::Borrowed(_) => Err(DoIpError::BufferTooSmall),
-            UdsBuffer::Owned(ref mut buf) => Ok(buf),
-        }?;
-        buffer.resize(user_data_len, 0u8);
-        reader.read_exact(buffer.as_mut())?;
-        Ok(())
+        read_user_data(reader, &mut self.user_data, user_data_len)
     }
 
     fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
@@ -77,15 +90,22 @@ impl<'a> Payload for DiagnosticMessage<'a> {
         writer.write_all(self.user_data.get_ref())?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        put_addrs(buf, self.source_address, self.target_address);
+        buf[4..].copy_from_slice(self.user_data.get_ref());
+        Ok(buf.len())
+    }
 }
 
 impl<'a> BorrowedPayload<'a> for DiagnosticMessage<'a> {
     fn read_borrowed(payload: &'a [u8]) -> Result<Self, DoIpError> {
         use DoIpError::*;
-        if payload.len() < size::DIAGREQ_DEFAULT_SIZE {
+        if payload.len() < DiagnosticMessage::MIN_LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload.len() as u32,
-                expected: size::DIAGREQ_DEFAULT_SIZE as u32,
+                expected: DiagnosticMessage::MIN_LENGTH as u32,
             });
         }
         let (source_address, target_address) = get_addrs(payload);
@@ -99,7 +119,8 @@ impl<'a> BorrowedPayload<'a> for DiagnosticMessage<'a> {
 
 impl<'a> Payload for DiagnosticMessagePositiveAck<'a> {
     fn length(&self) -> usize {
-        size::DIAGRSPACK_DEFAULT_SIZE + self.previous_diagnostic_message_data.get_ref().len()
+        DiagnosticMessagePositiveAck::MIN_LENGTH
+            + self.previous_diagnostic_message_data.get_ref().len()
     }
 
     fn payload_type() -> PayloadType {
@@ -118,23 +139,21 @@ impl<'a> Payload for DiagnosticMessagePositiveAck<'a> {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         use DoIpError::*;
-        if payload_length < size::DIAGRSPACK_DEFAULT_SIZE {
+        if payload_length < DiagnosticMessagePositiveAck::MIN_LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::DIAGRSPACK_DEFAULT_SIZE as u32,
+                expected: DiagnosticMessagePositiveAck::MIN_LENGTH as u32,
             });
         }
         (self.source_address, self.target_address) = read_addrs(reader)?;
         let previous_diagnostic_message_data_len = payload_length - 5; // 5 == Length
-        let buffer = match self.previous_diagnostic_message_data {
-            UdsBuffer::Borrowed(_) => Err(DoIpError::BufferTooSmall),
-            UdsBuffer::Owned(ref mut buf) => Ok(buf),
-        }?;
-        buffer.resize(previous_diagnostic_message_data_len, 0u8);
         let ack_code_raw = reader.read_u8()?;
         self.ack_code = DiagnosticMessagePositiveAckCode::from(ack_code_raw);
-        reader.read_exact(buffer)?;
-        Ok(())
+        read_user_data(
+            reader,
+            &mut self.previous_diagnostic_message_data,
+            previous_diagnostic_message_data_len,
+        )
     }
 
     fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
@@ -143,15 +162,23 @@ impl<'a> Payload for DiagnosticMessagePositiveAck<'a> {
         writer.write_all(self.previous_diagnostic_message_data.get_ref())?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        put_addrs(buf, self.source_address, self.target_address);
+        buf[4] = self.ack_code.into();
+        buf[5..].copy_from_slice(self.previous_diagnostic_message_data.get_ref());
+        Ok(buf.len())
+    }
 }
 
 impl<'a> BorrowedPayload<'a> for DiagnosticMessagePositiveAck<'a> {
     fn read_borrowed(payload: &'a [u8]) -> Result<Self, DoIpError> {
         use DoIpError::*;
-        if payload.len() < size::DIAGRSPACK_DEFAULT_SIZE {
+        if payload.len() < DiagnosticMessagePositiveAck::MIN_LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload.len() as u32,
-                expected: size::DIAGRSPACK_DEFAULT_SIZE as u32,
+                expected: DiagnosticMessagePositiveAck::MIN_LENGTH as u32,
             });
         }
         let (source_address, target_address) = get_addrs(payload);
@@ -168,7 +195,8 @@ impl<'a> BorrowedPayload<'a> for DiagnosticMessagePositiveAck<'a> {
 
 impl<'a> Payload for DiagnosticMessageNegativeAck<'a> {
     fn length(&self) -> usize {
-        size::DIAGRSPNACK_DEFAULT_SIZE + self.previous_diagnostic_message_data.get_ref().len()
+        DiagnosticMessageNegativeAck::MIN_LENGTH
+            + self.previous_diagnostic_message_data.get_ref().len()
     }
 
     fn payload_type() -> PayloadType {
@@ -186,17 +214,22 @@ impl<'a> Payload for DiagnosticMessageNegativeAck<'a> {
         reader: &mut T,
         payload_length: usize,
     ) -> Result<(), DoIpError> {
+        use DoIpError::PayloadLengthTooShort;
+        if payload_length < DiagnosticMessageNegativeAck::MIN_LENGTH {
+            return Err(PayloadLengthTooShort {
+                value: payload_length as u32,
+                expected: DiagnosticMessageNegativeAck::MIN_LENGTH as u32,
+            });
+        }
         (self.source_address, self.target_address) = read_addrs(reader)?;
         let previous_diagnostic_message_data_len = payload_length - 5; // 5 == Length
-        let buffer = match self.previous_diagnostic_message_data {
-            UdsBuffer::Borrowed(_) => Err(DoIpError::BufferTooSmall),
-            UdsBuffer::Owned(ref mut buf) => Ok(buf),
-        }?;
-        buffer.resize(previous_diagnostic_message_data_len, 0u8);
         let ack_code_raw = reader.read_u8()?;
         self.ack_code = DiagnosticMessageNegativeAckCode::from(ack_code_raw);
-        reader.read_exact(buffer)?;
-        Ok(())
+        read_user_data(
+            reader,
+            &mut self.previous_diagnostic_message_data,
+            previous_diagnostic_message_data_len,
+        )
     }
 
     fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
@@ -205,15 +238,23 @@ impl<'a> Payload for DiagnosticMessageNegativeAck<'a> {
         writer.write_all(self.previous_diagnostic_message_data.get_ref())?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        put_addrs(buf, self.source_address, self.target_address);
+        buf[4] = self.ack_code.into();
+        buf[5..].copy_from_slice(self.previous_diagnostic_message_data.get_ref());
+        Ok(buf.len())
+    }
 }
 
 impl<'a> BorrowedPayload<'a> for DiagnosticMessageNegativeAck<'a> {
     fn read_borrowed(payload: &'a [u8]) -> Result<Self, DoIpError> {
-        use DoIpError::*;
-        if payload.len() < size::DIAGRSPACK_DEFAULT_SIZE {
+        use DoIpError::PayloadLengthTooShort;
+        if payload.len() < DiagnosticMessageNegativeAck::MIN_LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload.len() as u32,
-                expected: size::DIAGRSPACK_DEFAULT_SIZE as u32,
+                expected: DiagnosticMessageNegativeAck::MIN_LENGTH as u32,
             });
         }
         let (source_address, target_address) = get_addrs(payload);
@@ -228,40 +269,32 @@ impl<'a> BorrowedPayload<'a> for DiagnosticMessageNegativeAck<'a> {
     }
 }
 
-mod size {
-    use super::LogicalAddress;
+pub(crate) mod size {
     use super::{
         DiagnosticMessage, DiagnosticMessageNegativeAck, DiagnosticMessageNegativeAckCode,
         DiagnosticMessagePositiveAck, DiagnosticMessagePositiveAckCode, UdsBuffer,
     };
-    use std::mem::size_of;
+    use alloc::vec::Vec;
 
     pub const DIAGREQ_ZEROED: DiagnosticMessage = DiagnosticMessage {
         source_address: 0u16,
         target_address: 0u16,
-        user_data: UdsBuffer::Owned(vec![]),
+        user_data: UdsBuffer::Owned(Vec::new()),
     };
-    pub const DIAGREQ_DEFAULT_SIZE: usize =
-        size_of::<LogicalAddress>() + size_of::<LogicalAddress>();
 
     pub const DIAGRSPACK_ZEROES: DiagnosticMessagePositiveAck = DiagnosticMessagePositiveAck {
         source_address: 0u16,
         target_address: 0u16,
         ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
-        previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+        previous_diagnostic_message_data: UdsBuffer::Owned(Vec::new()),
     };
-    pub const DIAGRSPACK_DEFAULT_SIZE: usize =
-        size_of::<LogicalAddress>() + size_of::<LogicalAddress>() + size_of::<u8>();
 
-    #[allow(dead_code)]
     pub const DIAGRSPNACK_ZEROES: DiagnosticMessageNegativeAck = DiagnosticMessageNegativeAck {
         source_address: 0u16,
         target_address: 0u16,
         ack_code: DiagnosticMessageNegativeAckCode::InvalidSourceAddress,
-        previous_diagnostic_message_data: UdsBuffer::Owned(vec![]),
+        previous_diagnostic_message_data: UdsBuffer::Owned(Vec::new()),
     };
-    pub const DIAGRSPNACK_DEFAULT_SIZE: usize =
-        size_of::<LogicalAddress>() + size_of::<LogicalAddress>() + size_of::<u8>();
 }
 
 impl From<u8> for DiagnosticMessagePositiveAckCode {
@@ -319,6 +352,7 @@ impl From<DiagnosticMessageNegativeAckCode> for u8 {
 mod test {
     use super::super::tests::*;
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn diagnostic_message() {
@@ -372,4 +406,21 @@ mod test {
         assert_encode(&payload, &v);
         assert_decode_no_length_change(&payload, &v);
     }
+
+    #[test]
+    fn diagnostic_message_truncated() {
+        let v = [0x01, 0x23, 0x00, 0xed, 0x22, 0xf0];
+        let res = DiagnosticMessage::read(&mut &v[..], 0xffff_fff0);
+        assert!(matches!(res, Err(DoIpError::Io(_))));
+        let res = DiagnosticMessageNegativeAck::read(&mut &v[..], 4);
+        assert!(matches!(res, Err(DoIpError::PayloadLengthTooShort { .. })));
+    }
+
+    #[test]
+    fn read_into_borrowed() {
+        let v = [0x01, 0x23, 0x00, 0xed, 0x22, 0xf0];
+        let mut payload = DiagnosticMessage::new(0x0000, 0x0000, &[][..]);
+        let res = payload.read_replace(&mut &v[..], v.len());
+        assert!(matches!(res, Err(DoIpError::BorrowedBuffer(2))));
+    }
 }
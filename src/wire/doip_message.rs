@@ -0,0 +1,466 @@
+use alloc::vec::Vec;
+
+use crate::io::{read_to_vec, Read, Write};
+
+use crate::message::*;
+use crate::{BorrowedPayload, DoIpError, DoIpMessage, Payload, PayloadType};
+
+impl<'a> DoIpMessage<'a> {
+    /// Get the length of this message payload.
+    pub fn length(&self) -> usize {
+        use DoIpMessage::*;
+        match self {
+            GenericDoIpHeaderNegativeAcknowledge(p) => p.length(),
+            VehicleIdentificationRequest(p) => p.length(),
+            VehicleIdentificationRequestWithEid(p) => p.length(),
+            VehicleIdentificationRequestWithVin(p) => p.length(),
+            VehicleIdentificationResponse(p) => p.length(),
+            RoutingActivationRequest(p) => p.length(),
+            RoutingActivationResponse(p) => p.length(),
+            AliveCheckRequest(p) => p.length(),
+            AliveCheckResponse(p) => p.length(),
+            EntityStatusRequest(p) => p.length(),
+            EntityStatusResponse(p) => p.length(),
+            PowerModeRequest(p) => p.length(),
+            PowerModeResponse(p) => p.length(),
+            DiagnosticMessage(p) => p.length(),
+            DiagnosticMessagePositiveAck(p) => p.length(),
+            DiagnosticMessageNegativeAck(p) => p.length(),
+            Unknown { bytes, .. } => bytes.get_ref().len(),
+        }
+    }
+
+    /// Reads from the reader `payload_length` bytes and decodes the message
+    /// according to `payload_type`.
+    pub fn read<T: Read>(
+        reader: &mut T,
+        payload_type: PayloadType,
+        payload_length: usize,
+    ) -> Result<Self, DoIpError> {
+        use DoIpMessage as M;
+        use PayloadType as PT;
+        let r = reader;
+        let len = payload_length;
+        Ok(match payload_type {
+            PT::GenericDoIpHeaderNegativeAcknowledge => {
+                M::GenericDoIpHeaderNegativeAcknowledge(Payload::read(r, len)?)
+            }
+            PT::VehicleIdentificationRequest => {
+                M::VehicleIdentificationRequest(Payload::read(r, len)?)
+            }
+            PT::VehicleIdentificationRequestWithEid => {
+                M::VehicleIdentificationRequestWithEid(Payload::read(r, len)?)
+            }
+            PT::VehicleIdentificationRequestWithVin => {
+                M::VehicleIdentificationRequestWithVin(Payload::read(r, len)?)
+            }
+            PT::VehicleIdentificationResponse => {
+                M::VehicleIdentificationResponse(Payload::read(r, len)?)
+            }
+            PT::RoutingActivationRequest => M::RoutingActivationRequest(Payload::read(r, len)?),
+            PT::RoutingActivationResponse => M::RoutingActivationResponse(Payload::read(r, len)?),
+            PT::AliveCheckRequest => M::AliveCheckRequest(Payload::read(r, len)?),
+            PT::AliveCheckResponse => M::AliveCheckResponse(Payload::read(r, len)?),
+            PT::DoIpEntityStatusRequest => M::EntityStatusRequest(Payload::read(r, len)?),
+            PT::DoIpEntityStatusResponse => M::EntityStatusResponse(Payload::read(r, len)?),
+            PT::DiagnosticPowerModeInformationRequest => {
+                M::PowerModeRequest(Payload::read(r, len)?)
+            }
+            PT::DiagnosticPowerModeInformationResponse => {
+                M::PowerModeResponse(Payload::read(r, len)?)
+            }
+            PT::DiagnosticMessage => M::DiagnosticMessage(Payload::read(r, len)?),
+            PT::DiagnosticMessagePositiveAcknowledgement => {
+                M::DiagnosticMessagePositiveAck(Payload::read(r, len)?)
+            }
+            PT::DiagnosticMessageNegativeAcknowledgement => {
+                M::DiagnosticMessageNegativeAck(Payload::read(r, len)?)
+            }
+            PT::Reserved(_) | PT::ReservedVm(_) => {
+                let mut bytes = Vec::new();
+                read_to_vec(r, &mut bytes, len)?;
+                M::Unknown {
+                    payload_type,
+                    bytes: UdsBuffer::Owned(bytes),
+                }
+            }
+        })
+    }
+
+    /// Decodes the message from the whole `payload` slice according to
+    /// `payload_type`, without any allocation.
+    ///
+    /// The user data of the diagnostic messages, and the raw bytes of the
+    /// [`DoIpMessage::Unknown`] payloads, are borrowed from `payload`.
+    pub fn read_borrowed(payload: &'a [u8], payload_type: PayloadType) -> Result<Self, DoIpError> {
+        use DoIpMessage as M;
+        use PayloadType as PT;
+        let p = payload;
+        Ok(match payload_type {
+            PT::GenericDoIpHeaderNegativeAcknowledge => {
+                M::GenericDoIpHeaderNegativeAcknowledge(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::VehicleIdentificationRequest => {
+                M::VehicleIdentificationRequest(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::VehicleIdentificationRequestWithEid => {
+                M::VehicleIdentificationRequestWithEid(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::VehicleIdentificationRequestWithVin => {
+                M::VehicleIdentificationRequestWithVin(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::VehicleIdentificationResponse => {
+                M::VehicleIdentificationResponse(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::RoutingActivationRequest => {
+                M::RoutingActivationRequest(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::RoutingActivationResponse => {
+                M::RoutingActivationResponse(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::AliveCheckRequest => M::AliveCheckRequest(BorrowedPayload::read_borrowed(p)?),
+            PT::AliveCheckResponse => M::AliveCheckResponse(BorrowedPayload::read_borrowed(p)?),
+            PT::DoIpEntityStatusRequest => {
+                M::EntityStatusRequest(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::DoIpEntityStatusResponse => {
+                M::EntityStatusResponse(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::DiagnosticPowerModeInformationRequest => {
+                M::PowerModeRequest(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::DiagnosticPowerModeInformationResponse => {
+                M::PowerModeResponse(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::DiagnosticMessage => M::DiagnosticMessage(BorrowedPayload::read_borrowed(p)?),
+            PT::DiagnosticMessagePositiveAcknowledgement => {
+                M::DiagnosticMessagePositiveAck(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::DiagnosticMessageNegativeAcknowledgement => {
+                M::DiagnosticMessageNegativeAck(BorrowedPayload::read_borrowed(p)?)
+            }
+            PT::Reserved(_) | PT::ReservedVm(_) => M::Unknown {
+                payload_type,
+                bytes: UdsBuffer::Borrowed(p),
+            },
+        })
+    }
+
+    /// Writes the DoIP payload of this message to a writer.
+    pub fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        use DoIpMessage::*;
+        match self {
+            GenericDoIpHeaderNegativeAcknowledge(p) => p.write(writer),
+            VehicleIdentificationRequest(p) => p.write(writer),
+            VehicleIdentificationRequestWithEid(p) => p.write(writer),
+            VehicleIdentificationRequestWithVin(p) => p.write(writer),
+            VehicleIdentificationResponse(p) => p.write(writer),
+            RoutingActivationRequest(p) => p.write(writer),
+            RoutingActivationResponse(p) => p.write(writer),
+            AliveCheckRequest(p) => p.write(writer),
+            AliveCheckResponse(p) => p.write(writer),
+            EntityStatusRequest(p) => p.write(writer),
+            EntityStatusResponse(p) => p.write(writer),
+            PowerModeRequest(p) => p.write(writer),
+            PowerModeResponse(p) => p.write(writer),
+            DiagnosticMessage(p) => p.write(writer),
+            DiagnosticMessagePositiveAck(p) => p.write(writer),
+            DiagnosticMessageNegativeAck(p) => p.write(writer),
+            Unknown { bytes, .. } => {
+                writer.write_all(bytes.get_ref())?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::message::*;
+    use crate::{parse_message, read_any_message, write_any_message, write_message, PayloadType};
+    use crate::{read_message_with, read_replace_message_with, UnexpectedPayloadPolicy};
+    use crate::{DoIpError, DoIpHeader, DOIP_HEADER_LENGTH};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn any_message_alive_check_request() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x07, // Payload type
+            0x00, 0x00, 0x00, 0x00, // Payload length
+        ];
+        let msg = read_any_message(&mut &v[..]).unwrap();
+        assert_eq!(msg, DoIpMessage::AliveCheckRequest(AliveCheckRequest {}));
+    }
+
+    #[test]
+    fn any_message_diagnostic_message() {
+        let payload = DiagnosticMessage {
+            source_address: 0x0123,
+            target_address: 0x00ed,
+            user_data: UdsBuffer::Owned(vec![0x22, 0xf0, 0x12]),
+        };
+        let mut v = vec![];
+        write_message(&payload, &mut v).unwrap();
+        let msg = read_any_message(&mut &v[..]).unwrap();
+        assert_eq!(msg.payload_type(), PayloadType::DiagnosticMessage);
+        assert_eq!(msg, DoIpMessage::DiagnosticMessage(payload));
+
+        let mut w = vec![];
+        write_any_message(&msg, &mut w).unwrap();
+        assert_eq!(v, w);
+    }
+
+    #[test]
+    fn any_message_unknown() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0xf0, 0x12, // Payload type
+            0x00, 0x00, 0x00, 0x03, // Payload length
+            0x01, 0x02, 0x03,
+        ];
+        let msg = read_any_message(&mut &v[..]).unwrap();
+        let expected = DoIpMessage::Unknown {
+            payload_type: PayloadType::ReservedVm(0xf012),
+            bytes: UdsBuffer::Owned(vec![0x01, 0x02, 0x03]),
+        };
+        assert_eq!(msg, expected);
+        assert_eq!(msg.length(), 3);
+
+        let mut w = vec![];
+        write_any_message(&msg, &mut w).unwrap();
+        assert_eq!(&v[..], &w);
+    }
+
+    #[test]
+    fn any_message_unknown_truncated() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0xf0, 0x12, // Payload type
+            0x00, 0x00, 0x00, 0x04, // Payload length
+            0x01, 0x02, 0x03,
+        ];
+        assert!(read_any_message(&mut &v[..]).is_err());
+    }
+
+    #[test]
+    fn any_message_wrong_length() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x08, // Payload type
+            0x00, 0x00, 0x00, 0x01, // Payload length
+            0x01,
+        ];
+        assert!(read_any_message(&mut &v[..]).is_err());
+    }
+
+    #[test]
+    fn parse_all_messages() {
+        let uds = [0x22, 0xf1, 0x90];
+        let messages = [
+            DoIpMessage::GenericDoIpHeaderNegativeAcknowledge(
+                GenericDoIpHeaderNegativeAcknowledge {
+                    nack_code: NegativeAckCode::MessageTooLarge,
+                },
+            ),
+            DoIpMessage::VehicleIdentificationRequest(VehicleIdentificationRequest {}),
+            DoIpMessage::VehicleIdentificationRequestWithEid(VehicleIdentificationRequestWithEid {
+                eid: Eid([1, 2, 3, 4, 5, 6]),
+            }),
+            DoIpMessage::VehicleIdentificationRequestWithVin(VehicleIdentificationRequestWithVin {
+                vin: Vin(*b"VF1YYYYYZTT000001"),
+            }),
+            DoIpMessage::VehicleIdentificationResponse(VehicleIdentificationResponse {
+                vin: Vin(*b"VF1YYYYYZTT000001"),
+                logical_address: 0x0077,
+                eid: Eid([1, 2, 3, 4, 5, 6]),
+                gid: Some([6, 5, 4, 3, 2, 1]),
+                further_action: FurtherActionRequired::NoFurtherActionRequired,
+                vin_gid_sync_status: None,
+            }),
+            DoIpMessage::RoutingActivationRequest(RoutingActivationRequest {
+                source_address: 0x0e00,
+                activation_type: ActivationType::Default,
+                reserved: [0; 4],
+                reserved_oem: Some([1, 2, 3, 4]),
+            }),
+            DoIpMessage::RoutingActivationResponse(RoutingActivationResponse {
+                logical_address_tester: 0x0e00,
+                logical_address_of_doip_entity: 0x0077,
+                routing_activation_response_code:
+                    RoutingActivationResponseCode::RoutingSuccessfullyActivated,
+                reserved_oem: [0; 4],
+                oem_specific: None,
+            }),
+            DoIpMessage::AliveCheckRequest(AliveCheckRequest {}),
+            DoIpMessage::AliveCheckResponse(AliveCheckResponse {
+                source_address: 0x0e00,
+            }),
+            DoIpMessage::EntityStatusRequest(EntityStatusRequest {}),
+            DoIpMessage::EntityStatusResponse(EntityStatusResponse {
+                node_type: NodeType::Gateway,
+                max_open_sockets: 4,
+                cur_open_sockets: 1,
+                max_data_size: Some(4096),
+            }),
+            DoIpMessage::PowerModeRequest(PowerModeRequest {}),
+            DoIpMessage::PowerModeResponse(PowerModeResponse {
+                power_mode: PowerMode::Ready,
+            }),
+            DoIpMessage::DiagnosticMessage(DiagnosticMessage {
+                source_address: 0x0e00,
+                target_address: 0x0077,
+                user_data: UdsBuffer::Borrowed(&uds),
+            }),
+            DoIpMessage::DiagnosticMessagePositiveAck(DiagnosticMessagePositiveAck {
+                source_address: 0x0077,
+                target_address: 0x0e00,
+                ack_code: DiagnosticMessagePositiveAckCode::RoutingConfirmationAck,
+                previous_diagnostic_message_data: UdsBuffer::Borrowed(&uds),
+            }),
+            DoIpMessage::DiagnosticMessageNegativeAck(DiagnosticMessageNegativeAck {
+                source_address: 0x0077,
+                target_address: 0x0e00,
+                ack_code: DiagnosticMessageNegativeAckCode::UnknownTargetAddress,
+                previous_diagnostic_message_data: UdsBuffer::Borrowed(&[]),
+            }),
+            DoIpMessage::Unknown {
+                payload_type: PayloadType::ReservedVm(0xf012),
+                bytes: UdsBuffer::Borrowed(&uds),
+            },
+        ];
+        let mut buf = vec![];
+        for message in messages.iter() {
+            write_any_message(message, &mut buf).unwrap();
+        }
+
+        let mut input = &buf[..];
+        for expected in messages.iter() {
+            let (header, message, length) = parse_message(input).unwrap();
+            assert_eq!(&message, expected);
+            assert_eq!(header.payload_type, expected.payload_type());
+            assert_eq!(length, DOIP_HEADER_LENGTH + expected.length());
+            input = &input[length..];
+        }
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn parse_incomplete_message() {
+        let mut buf = vec![];
+        write_message(
+            &AliveCheckResponse {
+                source_address: 0x0e00,
+            },
+            &mut buf,
+        )
+        .unwrap();
+        for len in 0..buf.len() {
+            let expected = if len < DOIP_HEADER_LENGTH {
+                DOIP_HEADER_LENGTH
+            } else {
+                buf.len()
+            };
+            match parse_message(&buf[..len]) {
+                Err(DoIpError::IncompleteMessage { value, expected: e }) => {
+                    assert_eq!((value, e), (len, expected))
+                }
+                r => panic!("unexpected result for length {}: {:?}", len, r),
+            }
+        }
+        assert!(parse_message(&buf).is_ok());
+    }
+
+    #[test]
+    fn parse_wrong_length() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x08, // Payload type
+            0x00, 0x00, 0x00, 0x01, // Payload length
+            0x01,
+        ];
+        assert!(parse_message(&v).is_err());
+    }
+
+    #[test]
+    fn parse_max_payload_length() {
+        let v = [0x02, 0xfd, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff, 0x0e, 0x00];
+        assert!(matches!(
+            parse_message(&v),
+            Err(DoIpError::IncompleteMessage { value: 10, .. }
+                | DoIpError::MessageTooLarge {
+                    value: 0xffff_ffff,
+                    ..
+                })
+        ));
+    }
+
+    fn unexpected_then_expected() -> Vec<u8> {
+        let mut v = vec![];
+        write_message(
+            &DiagnosticMessage::new(0x0077, 0x0e00, vec![0x7f, 0x22, 0x78]),
+            &mut v,
+        )
+        .unwrap();
+        write_message(
+            &AliveCheckResponse {
+                source_address: 0x0e00,
+            },
+            &mut v,
+        )
+        .unwrap();
+        v
+    }
+
+    #[test]
+    fn unexpected_payload_leave() {
+        let v = unexpected_then_expected();
+        let mut input = &v[..];
+        let res: Result<AliveCheckResponse, _> =
+            read_message_with(&mut input, UnexpectedPayloadPolicy::Leave);
+        assert!(matches!(
+            res,
+            Err(DoIpError::UnexpectedPayloadType { value: 0x8001 })
+        ));
+        assert_eq!(input.len(), v.len() - DOIP_HEADER_LENGTH);
+    }
+
+    #[test]
+    fn unexpected_payload_discard() {
+        let v = unexpected_then_expected();
+        let mut input = &v[..];
+        let mut response = AliveCheckResponse { source_address: 0 };
+        let res =
+            read_replace_message_with(&mut response, &mut input, UnexpectedPayloadPolicy::Discard);
+        assert!(matches!(
+            res,
+            Err(DoIpError::UnexpectedPayloadType { value: 0x8001 })
+        ));
+        read_replace_message_with(&mut response, &mut input, UnexpectedPayloadPolicy::Discard)
+            .unwrap();
+        assert_eq!(response.source_address, 0x0e00);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn unexpected_payload_return() {
+        let v = unexpected_then_expected();
+        let mut input = &v[..];
+        let res: Result<AliveCheckResponse, _> =
+            read_message_with(&mut input, UnexpectedPayloadPolicy::Return);
+        let Err(DoIpError::UnexpectedMessage { header, payload }) = res else {
+            panic!("unexpected result {:?}", res);
+        };
+        assert_eq!(header, DoIpHeader::new(PayloadType::DiagnosticMessage, 7));
+        assert_eq!(payload, [0x00, 0x77, 0x0e, 0x00, 0x7f, 0x22, 0x78]);
+        let response: AliveCheckResponse =
+            read_message_with(&mut input, UnexpectedPayloadPolicy::Return).unwrap();
+        assert_eq!(response.source_address, 0x0e00);
+
+        let mut truncated = &v[..DOIP_HEADER_LENGTH + 3];
+        let res: Result<AliveCheckResponse, _> =
+            read_message_with(&mut truncated, UnexpectedPayloadPolicy::Return);
+        assert!(matches!(res, Err(DoIpError::Io(_))));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder};
+
+use super::super::proto::entity_status::*;
+use super::payload::payload_buffer;
+use crate::{BorrowedPayload, DoIpError, Payload, PayloadType};
+
+impl Payload for EntityStatusRequest {
+    fn length(&self) -> usize {
+        0
+    }
+
+    fn payload_type() -> PayloadType {
+        PayloadType::DoIpEntityStatusRequest
+    }
+
+    fn read<T: Read>(_reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        assert_empty_payload(payload_length)?;
+        Ok(EntityStatusRequest {})
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        _reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        assert_empty_payload(payload_length)
+    }
+
+    fn write<T: Write>(&self, _writer: &mut T) -> Result<(), DoIpError> {
+        Ok(())
+    }
+
+    fn encode_into(&self, _buf: &mut [u8]) -> Result<usize, DoIpError> {
+        Ok(0)
+    }
+}
+
+impl BorrowedPayload<'_> for EntityStatusRequest {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+impl Payload for EntityStatusResponse {
+    fn length(&self) -> usize {
+        self.max_data_size
+            .map(|_| EntityStatusResponse::MAX_DATA_SIZE_LENGTH)
+            .unwrap_or(EntityStatusResponse::LENGTH)
+    }
+
+    fn payload_type() -> PayloadType {
+        PayloadType::DoIpEntityStatusResponse
+    }
+
+    fn read<T: Read>(reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        let mut me = size::ESRSP_ZEROED;
+        me.read_replace(reader, payload_length)?;
+        Ok(me)
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        use DoIpError::*;
+        let has_max_data_size = match payload_length {
+            EntityStatusResponse::LENGTH => Ok(false),
+            EntityStatusResponse::MAX_DATA_SIZE_LENGTH => Ok(true),
+            _ => Err(PayloadLengthTooShort {
+                value: payload_length as u32,
+                expected: EntityStatusResponse::LENGTH as u32,
+            }),
+        }?;
+        self.node_type = NodeType::from(reader.read_u8()?);
+        self.max_open_sockets = reader.read_u8()?;
+        self.cur_open_sockets = reader.read_u8()?;
+        self.max_data_size = match has_max_data_size {
+            true => Some(reader.read_u32::<BigEndian>()?),
+            false => None,
+        };
+        Ok(())
+    }
+
+    fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        writer.write_u8(self.node_type.into())?;
+        writer.write_u8(self.max_open_sockets)?;
+        writer.write_u8(self.cur_open_sockets)?;
+        if let Some(max_data_size) = self.max_data_size {
+            writer.write_u32::<BigEndian>(max_data_size)?;
+        }
+        Ok(())
+    }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        buf[0] = self.node_type.into();
+        buf[1] = self.max_open_sockets;
+        buf[2] = self.cur_open_sockets;
+        if let Some(max_data_size) = self.max_data_size {
+            BigEndian::write_u32(&mut buf[3..7], max_data_size);
+        }
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for EntityStatusResponse {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+pub(crate) mod size {
+    use super::{EntityStatusResponse, NodeType};
+
+    pub const ESRSP_ZEROED: EntityStatusResponse = EntityStatusResponse {
+        node_type: NodeType::Gateway,
+        max_open_sockets: 0,
+        cur_open_sockets: 0,
+        max_data_size: Some(0),
+    };
+}
+
+impl From<u8> for NodeType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => NodeType::Gateway,
+            0x01 => NodeType::Node,
+            _ => NodeType::Reserved(value),
+        }
+    }
+}
+
+impl From<NodeType> for u8 {
+    fn from(value: NodeType) -> Self {
+        match value {
+            NodeType::Gateway => 0x00,
+            NodeType::Node => 0x01,
+            NodeType::Reserved(value) => value,
+        }
+    }
+}
+
+fn assert_empty_payload(payload_length: usize) -> Result<(), DoIpError> {
+    use DoIpError::*;
+    if payload_length != 0 {
+        return Err(PayloadLengthTooShort {
+            value: payload_length as u32,
+            expected: 0u32,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::tests::*;
+    use super::*;
+    use alloc::vec;
+    #[test]
+    fn entity_status_request() {
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x40, 0x01, // Payload type
+            0x00, 0x00, 0x00, 0x00, // Payload length
+        ];
+        assert_encode(&EntityStatusRequest {}, &v);
+        assert_decode(&EntityStatusRequest {}, &v);
+    }
+    #[test]
+    fn entity_status_response() {
+        let payload = EntityStatusResponse {
+            node_type: NodeType::Reserved(0x12),
+            max_open_sockets: 2,
+            cur_open_sockets: 1,
+            max_data_size: Some(1024),
+        };
+        let v = vec![
+            0x02, 0xfd, // Protocol version
+            0x40, 0x02, // Payload type
+            0x00, 0x00, 0x00, 0x07, // Payload length
+            0x12, 0x02, 0x01, 0x00, 0x00, 0x04, 0x00,
+        ];
+        assert_encode(&payload, &v);
+        assert_decode(&payload, &v);
+    }
+    #[test]
+    fn entity_status_response_without_max_data_size() {
+        let payload = EntityStatusResponse {
+            node_type: NodeType::Gateway,
+            max_open_sockets: 2,
+            cur_open_sockets: 1,
+            max_data_size: None,
+        };
+        let v = vec![
+            0x02, 0xfd, // Protocol version
+            0x40, 0x02, // Payload type
+            0x00, 0x00, 0x00, 0x03, // Payload length
+            0x00, 0x02, 0x01,
+        ];
+        assert_encode(&payload, &v);
+        assert_decode(&payload, &v);
+    }
+    #[test]
+    fn node_type() {
+        assert_eq!(NodeType::from(0x00), NodeType::Gateway);
+        assert_eq!(NodeType::from(0x01), NodeType::Node);
+        assert_eq!(NodeType::from(0x02), NodeType::Reserved(0x02));
+        for value in 0..=0xff {
+            assert_eq!(u8::from(NodeType::from(value)), value);
+        }
+    }
+}
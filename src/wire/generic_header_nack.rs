@@ -1,12 +1,12 @@
-use byteorder::{ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
 
 use super::super::proto::generic_header_nack::*;
-use crate::{DoIpError, Payload, PayloadType};
+use super::payload::payload_buffer;
+use crate::{BorrowedPayload, DoIpError, Payload, PayloadType};
 
 impl Payload for GenericDoIpHeaderNegativeAcknowledge {
     fn length(&self) -> usize {
-        size::GENERIC_HEADER_NACK_DEFAULT_SIZE
+        GenericDoIpHeaderNegativeAcknowledge::LENGTH
     }
 
     fn payload_type() -> PayloadType {
@@ -25,10 +25,10 @@ impl Payload for GenericDoIpHeaderNegativeAcknowledge {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         use DoIpError::*;
-        if payload_length != size::GENERIC_HEADER_NACK_DEFAULT_SIZE {
+        if payload_length != GenericDoIpHeaderNegativeAcknowledge::LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::GENERIC_HEADER_NACK_DEFAULT_SIZE as u32,
+                expected: GenericDoIpHeaderNegativeAcknowledge::LENGTH as u32,
             });
         }
         let nack_code = reader.read_u8()?;
@@ -41,18 +41,28 @@ impl Payload for GenericDoIpHeaderNegativeAcknowledge {
         writer.write_u8(nack_code)?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, Self::LENGTH)?;
+        buf[0] = self.nack_code.into();
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for GenericDoIpHeaderNegativeAcknowledge {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
-mod size {
+pub(crate) mod size {
     use super::{GenericDoIpHeaderNegativeAcknowledge, NegativeAckCode};
-    use std::mem::size_of;
 
     pub const GENERIC_HEADER_NACK_ZEROED: GenericDoIpHeaderNegativeAcknowledge =
         GenericDoIpHeaderNegativeAcknowledge {
             nack_code: NegativeAckCode::IncorrectPatternFormat,
         };
-
-    pub const GENERIC_HEADER_NACK_DEFAULT_SIZE: usize = size_of::<u8>();
 }
 
 impl From<NegativeAckCode> for u8 {
@@ -87,6 +97,7 @@ impl From<u8> for NegativeAckCode {
 mod test {
     use super::super::tests::*;
     use super::*;
+    use alloc::vec;
     #[test]
     fn generic_header_nack() {
         let payload = GenericDoIpHeaderNegativeAcknowledge {
@@ -0,0 +1,249 @@
+use crate::io::{Read, Write, WriteBytesExt};
+use crate::DoIpError;
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::message::ProtocolVersion;
+use crate::{DoIpHeader, HeaderValidator, PayloadType, DOIP_HEADER_LENGTH};
+
+/// DoIP header
+///
+/// This is the first part of a DoIP message, always followed by a DoIP payload.
+/// The header has always a fixed number of bytes.
+impl DoIpHeader {
+    /// Read a DoIP header from the reader.
+    ///
+    /// The 8 bytes of the header are read at once, with a single
+    /// `read_exact()`.
+    pub fn read<T: Read>(reader: &mut T) -> Result<Self, DoIpError> {
+        let mut bytes = [0u8; DOIP_HEADER_LENGTH];
+        reader.read_exact(&mut bytes)?;
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// Decodes a DoIP header from its 8 bytes.
+    pub fn from_bytes(bytes: &[u8; DOIP_HEADER_LENGTH]) -> Self {
+        DoIpHeader {
+            protocol_version: bytes[0],
+            inverse_protocol_version: bytes[1],
+            payload_type: PayloadType::from(BigEndian::read_u16(&bytes[2..4])),
+            payload_length: BigEndian::read_u32(&bytes[4..8]),
+        }
+    }
+    /// Encodes a DoIP header into its 8 bytes.
+    pub fn to_bytes(&self) -> [u8; DOIP_HEADER_LENGTH] {
+        let mut bytes = [0u8; DOIP_HEADER_LENGTH];
+        bytes[0] = self.protocol_version;
+        bytes[1] = self.inverse_protocol_version;
+        BigEndian::write_u16(&mut bytes[2..4], self.payload_type.into_u16());
+        BigEndian::write_u32(&mut bytes[4..8], self.payload_length);
+        bytes
+    }
+
+    /// Writes a DoIP header to the writer.
+    pub fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        writer.write_u8(self.protocol_version)?;
+        writer.write_u8(self.inverse_protocol_version)?;
+        writer.write_u16::<BigEndian>(self.payload_type.into_u16())?;
+        writer.write_u32::<BigEndian>(self.payload_length)?;
+        Ok(())
+    }
+}
+
+impl HeaderValidator {
+    /// Validates a received DoIP header.
+    ///
+    /// The checks are run in the order mandated by ISO 13400-2, and the first
+    /// failing one is returned as an error.
+    pub fn validate(&self, header: &DoIpHeader) -> Result<(), DoIpError> {
+        use DoIpError::*;
+        if !has_valid_pattern(header) {
+            return Err(IncorrectPatternFormat {
+                protocol_version: header.protocol_version,
+                inverse_protocol_version: header.inverse_protocol_version,
+            });
+        }
+        if let PayloadType::Reserved(value) | PayloadType::ReservedVm(value) = header.payload_type {
+            return Err(UnknownPayloadType(value));
+        }
+        if header.payload_length > self.max_payload_length {
+            return Err(MessageTooLarge {
+                value: header.payload_length,
+                max: self.max_payload_length,
+            });
+        }
+        if !header
+            .payload_type
+            .is_valid_length(header.payload_length as usize)
+        {
+            return Err(InvalidPayloadLength {
+                value: header.payload_length,
+                payload_type: header.payload_type.into_u16(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn has_valid_pattern(header: &DoIpHeader) -> bool {
+    use PayloadType::*;
+    let version = header.protocol_version;
+    let supported_version = if version == ProtocolVersion::VehicleIdentificationRequest as u8 {
+        // Default protocol version, only for vehicle identification requests.
+        matches!(
+            header.payload_type,
+            VehicleIdentificationRequest
+                | VehicleIdentificationRequestWithEid
+                | VehicleIdentificationRequestWithVin
+        )
+    } else {
+        ProtocolVersion::try_from(version).is_ok()
+    };
+    supported_version && header.inverse_protocol_version == !version
+}
+
+impl TryFrom<u8> for ProtocolVersion {
+    type Error = DoIpError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(ProtocolVersion::DoIpIsoDis),
+            0x02 => Ok(ProtocolVersion::DoIpIso),
+            0x03 => Ok(ProtocolVersion::Iso13400_2019),
+            0x04 => Ok(ProtocolVersion::DoIpIsoV4),
+            0xFF => Ok(ProtocolVersion::VehicleIdentificationRequest),
+            _ => Err(DoIpError::UnknownProtocolVersion(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{message::ProtocolVersion, DOIP_HEADER_LENGTH};
+
+    #[test]
+    fn test_serialize() {
+        let mut buff = [0; DOIP_HEADER_LENGTH];
+        let header = DoIpHeader::new(PayloadType::RoutingActivationRequest, 11);
+        let res = header.write(&mut &mut buff[..]);
+        assert!(res.is_ok());
+        let expected = [0x02u8, 0xfd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b];
+        assert_eq!(expected, buff);
+    }
+
+    #[test]
+    fn test_derialize() {
+        let mut buff: &[u8] = &[0x02u8, 0xfd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b];
+        let header = DoIpHeader::read(&mut buff).unwrap();
+        assert_eq!(header.protocol_version, ProtocolVersion::DoIpIso as u8);
+        assert_eq!(header.payload_type, PayloadType::RoutingActivationRequest);
+        assert_eq!(header.payload_length, 11u32);
+    }
+
+    fn validate(input: [u8; DOIP_HEADER_LENGTH]) -> Result<(), DoIpError> {
+        let header = DoIpHeader::read(&mut &input[..]).unwrap();
+        HeaderValidator::new(4096).validate(&header)
+    }
+
+    #[test]
+    fn test_validate() {
+        use crate::message::NegativeAckCode;
+        let nack_code = |e: DoIpError| e.header_nack().unwrap().nack_code;
+
+        assert!(validate([0x02, 0xfd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).is_ok());
+        assert!(validate([0x01, 0xfe, 0x80, 0x01, 0x00, 0x00, 0x10, 0x00]).is_ok());
+        assert!(validate([0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]).is_ok());
+        assert!(validate([0x03, 0xfc, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).is_ok());
+        assert!(validate([0x04, 0xfb, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).is_ok());
+
+        let err = validate([0x02, 0xfe, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::IncorrectPatternFormat);
+        let err = validate([0x07, 0xf8, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::IncorrectPatternFormat);
+        let err = validate([0xff, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0b]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::IncorrectPatternFormat);
+
+        let err = validate([0x02, 0xfd, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::UnknownPayloadType);
+
+        let err = validate([0x02, 0xfd, 0x80, 0x01, 0xff, 0xff, 0xff, 0xff]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::MessageTooLarge);
+        let err = validate([0x02, 0xfd, 0x80, 0x01, 0x00, 0x00, 0x10, 0x01]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::MessageTooLarge);
+
+        let err = validate([0x02, 0xfd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0a]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::InvalidPayloadLength);
+        let err = validate([0x02, 0xfd, 0x80, 0x02, 0x00, 0x00, 0x00, 0x04]).unwrap_err();
+        assert_eq!(nack_code(err), NegativeAckCode::InvalidPayloadLength);
+    }
+
+    #[test]
+    fn test_version_policy() {
+        use crate::message::{ProtocolVersion, VersionPolicy};
+        let header =
+            |version| DoIpHeader::new_versionned(version, PayloadType::AliveCheckRequest, 0);
+
+        let mut policy = VersionPolicy::default();
+        policy.learn(&header(ProtocolVersion::Iso13400_2019));
+        assert_eq!(policy.version(), ProtocolVersion::DoIpIso);
+
+        let mut policy = VersionPolicy::Echo(ProtocolVersion::DoIpIso);
+        policy.learn(&header(ProtocolVersion::VehicleIdentificationRequest));
+        assert_eq!(policy.version(), ProtocolVersion::DoIpIso);
+        policy.learn(&header(ProtocolVersion::Iso13400_2019));
+        assert_eq!(policy.version(), ProtocolVersion::Iso13400_2019);
+        policy.learn(&header(ProtocolVersion::DoIpIsoDis));
+        assert_eq!(policy.version(), ProtocolVersion::Iso13400_2019);
+    }
+
+    #[test]
+    fn test_framed() {
+        use crate::message::*;
+        use crate::{read_framed, write_framed, Framed};
+        use alloc::vec::Vec;
+
+        let input = [
+            0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // Vehicle identification request
+            0x01, 0xfe, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, // Alive check request
+        ];
+        let mut reader = &input[..];
+        let request: Framed<VehicleIdentificationRequest> = read_framed(&mut reader).unwrap();
+        assert_eq!(request.header.protocol_version, 0xff);
+        let response = request.reply(AliveCheckResponse { source_address: 0 });
+        assert_eq!(
+            (
+                response.header.protocol_version,
+                response.header.inverse_protocol_version
+            ),
+            (0x02, 0xfd)
+        );
+        let request: Framed<AliveCheckRequest> = read_framed(&mut reader).unwrap();
+        assert_eq!(
+            request.header,
+            DoIpHeader::new_versionned(
+                ProtocolVersion::DoIpIsoDis,
+                PayloadType::AliveCheckRequest,
+                0
+            )
+        );
+
+        let mut response = request.reply(AliveCheckResponse { source_address: 0 });
+        response.payload.source_address = 0x0e00;
+        let mut output = Vec::new();
+        write_framed(&response, &mut output).unwrap();
+        assert_eq!(
+            output,
+            [0x01, 0xfe, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x0e, 0x00]
+        );
+
+        let framed = Framed::new(ProtocolVersion::Iso13400_2019, AliveCheckRequest {});
+        let mut output = Vec::new();
+        write_framed(&framed, &mut output).unwrap();
+        assert_eq!(output, [0x03, 0xfc, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00]);
+        let mut reader = &output[..];
+        assert!(matches!(
+            read_framed::<_, AliveCheckResponse>(&mut reader),
+            Err(DoIpError::UnexpectedPayloadType { value: 0x0007 })
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{message, DoIpError, PayloadType};
+
+/// Get the first `length` bytes of `buf`, to encode a payload into.
+pub(crate) fn payload_buffer(buf: &mut [u8], length: usize) -> Result<&mut [u8], DoIpError> {
+    let value = buf.len();
+    buf.get_mut(..length).ok_or(DoIpError::BufferTooSmall {
+        value,
+        expected: length,
+    })
+}
+
+impl PayloadType {
+    /// Check whether a payload length is compatible with this payload type.
+    ///
+    /// Payload types not handled by this crate accept any length.
+    pub fn is_valid_length(self, payload_length: usize) -> bool {
+        use PayloadType::*;
+
+        match self {
+            GenericDoIpHeaderNegativeAcknowledge => {
+                payload_length == message::GenericDoIpHeaderNegativeAcknowledge::LENGTH
+            }
+            VehicleIdentificationRequest => payload_length == 0,
+            VehicleIdentificationRequestWithEid => {
+                payload_length == message::VehicleIdentificationRequestWithEid::LENGTH
+            }
+            VehicleIdentificationRequestWithVin => {
+                payload_length == message::VehicleIdentificationRequestWithVin::LENGTH
+            }
+            VehicleIdentificationResponse => {
+                payload_length == message::VehicleIdentificationResponse::LENGTH
+                    || payload_length == message::VehicleIdentificationResponse::SYNC_LENGTH
+            }
+            RoutingActivationRequest => {
+                payload_length == message::RoutingActivationRequest::LENGTH
+                    || payload_length == message::RoutingActivationRequest::OEM_LENGTH
+            }
+            RoutingActivationResponse => {
+                payload_length == message::RoutingActivationResponse::LENGTH
+                    || payload_length == message::RoutingActivationResponse::OEM_LENGTH
+            }
+            AliveCheckRequest => payload_length == 0,
+            AliveCheckResponse => payload_length == message::AliveCheckResponse::LENGTH,
+            DoIpEntityStatusRequest => payload_length == 0,
+            DoIpEntityStatusResponse => {
+                payload_length == message::EntityStatusResponse::LENGTH
+                    || payload_length == message::EntityStatusResponse::MAX_DATA_SIZE_LENGTH
+            }
+            DiagnosticPowerModeInformationRequest => payload_length == 0,
+            DiagnosticPowerModeInformationResponse => {
+                payload_length == message::PowerModeResponse::LENGTH
+            }
+            DiagnosticMessage => payload_length >= message::DiagnosticMessage::MIN_LENGTH,
+            DiagnosticMessagePositiveAcknowledgement => {
+                payload_length >= message::DiagnosticMessagePositiveAck::MIN_LENGTH
+            }
+            DiagnosticMessageNegativeAcknowledgement => {
+                payload_length >= message::DiagnosticMessageNegativeAck::MIN_LENGTH
+            }
+            Reserved(_) | ReservedVm(_) => true,
+        }
+    }
+
+    /// Convert a [`PayloadType`] into a u16.
+    pub fn into_u16(self) -> u16 {
+        use PayloadType::*;
+
+        match self {
+            GenericDoIpHeaderNegativeAcknowledge => 0x0000,
+            VehicleIdentificationRequest => 0x0001,
+            VehicleIdentificationRequestWithEid => 0x0002,
+            VehicleIdentificationRequestWithVin => 0x0003,
+            VehicleIdentificationResponse => 0x0004,
+            RoutingActivationRequest => 0x0005,
+            RoutingActivationResponse => 0x0006,
+            AliveCheckRequest => 0x0007,
+            AliveCheckResponse => 0x0008,
+            DoIpEntityStatusRequest => 0x4001,
+            DoIpEntityStatusResponse => 0x4002,
+            DiagnosticPowerModeInformationRequest => 0x4003,
+            DiagnosticPowerModeInformationResponse => 0x4004,
+            DiagnosticMessage => 0x8001,
+            DiagnosticMessagePositiveAcknowledgement => 0x8002,
+            DiagnosticMessageNegativeAcknowledgement => 0x8003,
+            Reserved(value) => value,
+            ReservedVm(value) => value,
+        }
+    }
+}
+
+impl From<u16> for PayloadType {
+    /// Convert a u16 into a [`PayloadType`].
+    fn from(value: u16) -> Self {
+        use PayloadType::*;
+        match value {
+            0x0000 => GenericDoIpHeaderNegativeAcknowledge,
+            0x0001 => VehicleIdentificationRequest,
+            0x0002 => VehicleIdentificationRequestWithEid,
+            0x0003 => VehicleIdentificationRequestWithVin,
+            0x0004 => VehicleIdentificationResponse,
+            0x0005 => RoutingActivationRequest,
+            0x0006 => RoutingActivationResponse,
+            0x0007 => AliveCheckRequest,
+            0x0008 => AliveCheckResponse,
+            0x0009..=0x4000 => Reserved(value),
+            0x4001 => DoIpEntityStatusRequest,
+            0x4002 => DoIpEntityStatusResponse,
+            0x4003 => DiagnosticPowerModeInformationRequest,
+            0x4004 => DiagnosticPowerModeInformationResponse,
+            0x4005..=0x8000 => Reserved(value),
+            0x8001 => DiagnosticMessage,
+            0x8002 => DiagnosticMessagePositiveAcknowledgement,
+            0x8003 => DiagnosticMessageNegativeAcknowledgement,
+            0x8004..=0xEFFF => Reserved(value),
+            0xF000..=0xFFFF => ReservedVm(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::io::{Read, Write};
+    use crate::{encode_message_into, write_message, DoIpError, Payload, PayloadType};
+    use alloc::vec;
+
+    /// Payload relying on the default [`Payload::encode_into()`].
+    struct Custom([u8; 3]);
+
+    impl Payload for Custom {
+        fn payload_type() -> PayloadType {
+            PayloadType::ReservedVm(0xf010)
+        }
+
+        fn length(&self) -> usize {
+            self.0.len()
+        }
+
+        fn read<T: Read>(reader: &mut T, _payload_length: usize) -> Result<Self, DoIpError> {
+            let mut data = [0; 3];
+            reader.read_exact(&mut data)?;
+            Ok(Self(data))
+        }
+
+        fn read_replace<T: Read>(
+            &mut self,
+            reader: &mut T,
+            payload_length: usize,
+        ) -> Result<(), DoIpError> {
+            *self = Self::read(reader, payload_length)?;
+            Ok(())
+        }
+
+        fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+            writer.write_all(&self.0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn default_encode_into() {
+        let payload = Custom([0x01, 0x02, 0x03]);
+        let mut expected = vec![];
+        write_message(&payload, &mut expected).unwrap();
+        let mut buf = [0xff; 16];
+        assert_eq!(encode_message_into(&payload, &mut buf).unwrap(), 11);
+        assert_eq!(buf[..11], expected[..]);
+        assert!(matches!(
+            payload.encode_into(&mut buf[..2]),
+            Err(DoIpError::BufferTooSmall {
+                value: 2,
+                expected: 3
+            })
+        ));
+    }
+}
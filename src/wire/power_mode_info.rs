@@ -1,8 +1,8 @@
-use byteorder::{ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
 
 use super::super::proto::power_mode_info::*;
-use crate::{DoIpError, Payload, PayloadType};
+use super::payload::payload_buffer;
+use crate::{BorrowedPayload, DoIpError, Payload, PayloadType};
 
 impl Payload for PowerModeRequest {
     fn length(&self) -> usize {
@@ -29,11 +29,22 @@ impl Payload for PowerModeRequest {
     fn write<T: Write>(&self, _writer: &mut T) -> Result<(), DoIpError> {
         Ok(())
     }
+
+    fn encode_into(&self, _buf: &mut [u8]) -> Result<usize, DoIpError> {
+        Ok(0)
+    }
+}
+
+impl BorrowedPayload<'_> for PowerModeRequest {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
 impl Payload for PowerModeResponse {
     fn length(&self) -> usize {
-        size::PMRSP_DEFAULT_SIZE
+        PowerModeResponse::LENGTH
     }
 
     fn payload_type() -> PayloadType {
@@ -52,28 +63,63 @@ impl Payload for PowerModeResponse {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         use DoIpError::*;
-        if payload_length != size::PMRSP_DEFAULT_SIZE {
+        if payload_length != PowerModeResponse::LENGTH {
             return Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::PMRSP_DEFAULT_SIZE as u32,
+                expected: PowerModeResponse::LENGTH as u32,
             });
         }
-        self.power_mode = reader.read_u8()?;
+        self.power_mode = PowerMode::from(reader.read_u8()?);
         Ok(())
     }
 
     fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
-        writer.write_u8(self.power_mode)?;
+        writer.write_u8(self.power_mode.into())?;
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, Self::LENGTH)?;
+        buf[0] = self.power_mode.into();
+        Ok(buf.len())
+    }
 }
 
-mod size {
-    use super::PowerModeResponse;
-    use std::mem::size_of;
+impl BorrowedPayload<'_> for PowerModeResponse {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
 
-    pub const PMRSP_ZEROED: PowerModeResponse = PowerModeResponse { power_mode: 0 };
-    pub const PMRSP_DEFAULT_SIZE: usize = size_of::<u8>();
+pub(crate) mod size {
+    use super::{PowerMode, PowerModeResponse};
+
+    pub const PMRSP_ZEROED: PowerModeResponse = PowerModeResponse {
+        power_mode: PowerMode::NotReady,
+    };
+}
+
+impl From<u8> for PowerMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => PowerMode::NotReady,
+            0x01 => PowerMode::Ready,
+            0x02 => PowerMode::NotSupported,
+            _ => PowerMode::Reserved(value),
+        }
+    }
+}
+
+impl From<PowerMode> for u8 {
+    fn from(value: PowerMode) -> Self {
+        match value {
+            PowerMode::NotReady => 0x00,
+            PowerMode::Ready => 0x01,
+            PowerMode::NotSupported => 0x02,
+            PowerMode::Reserved(value) => value,
+        }
+    }
 }
 
 fn assert_empty_payload(payload_length: usize) -> Result<(), DoIpError> {
@@ -91,6 +137,7 @@ fn assert_empty_payload(payload_length: usize) -> Result<(), DoIpError> {
 mod test {
     use super::super::tests::*;
     use super::*;
+    use alloc::vec;
     #[test]
     fn power_mode_request() {
         let v = vec![
@@ -103,7 +150,9 @@ mod test {
     }
     #[test]
     fn power_mode_response() {
-        let payload = PowerModeResponse { power_mode: 0x01 };
+        let payload = PowerModeResponse {
+            power_mode: PowerMode::Ready,
+        };
         let v = vec![
             0x02, 0xfd, // Protocol version
             0x40, 0x04, // Payload type
@@ -114,4 +163,14 @@ mod test {
         assert_encode(&payload, &v);
         assert_decode(&payload, &v);
     }
+    #[test]
+    fn power_mode() {
+        assert_eq!(PowerMode::from(0x00), PowerMode::NotReady);
+        assert_eq!(PowerMode::from(0x01), PowerMode::Ready);
+        assert_eq!(PowerMode::from(0x02), PowerMode::NotSupported);
+        assert_eq!(PowerMode::from(0x03), PowerMode::Reserved(0x03));
+        for value in 0..=0xff {
+            assert_eq!(u8::from(PowerMode::from(value)), value);
+        }
+    }
 }
@@ -1,17 +1,18 @@
 use crate::DoIpError;
 use crate::DoIpError::*;
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder};
 
+use super::payload::payload_buffer;
 use crate::proto::routing_activation::*;
-use crate::{Payload, PayloadType};
+use crate::{BorrowedPayload, Payload, PayloadType};
 
 impl Payload for RoutingActivationRequest {
     fn length(&self) -> usize {
         self.reserved_oem
-            .map(|_| size::RAREQ_OEM_SIZE)
-            .unwrap_or(size::RAREQ_DEFAULT_SIZE)
+            .map(|_| RoutingActivationRequest::OEM_LENGTH)
+            .unwrap_or(RoutingActivationRequest::LENGTH)
     }
 
     fn payload_type() -> PayloadType {
@@ -30,11 +31,11 @@ impl Payload for RoutingActivationRequest {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         let has_oem_data: bool = match payload_length {
-            size::RAREQ_DEFAULT_SIZE => Ok(false),
-            size::RAREQ_OEM_SIZE => Ok(true),
+            RoutingActivationRequest::LENGTH => Ok(false),
+            RoutingActivationRequest::OEM_LENGTH => Ok(true),
             _ => Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::RAREQ_DEFAULT_SIZE as u32,
+                expected: RoutingActivationRequest::LENGTH as u32,
             }),
         }?;
         self.source_address = reader.read_u16::<BigEndian>()?;
@@ -62,13 +63,31 @@ impl Payload for RoutingActivationRequest {
         }
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        BigEndian::write_u16(&mut buf[0..2], self.source_address);
+        buf[2] = self.activation_type as u8;
+        buf[3..7].copy_from_slice(&self.reserved);
+        if let Some(reserved_oem) = self.reserved_oem {
+            buf[7..11].copy_from_slice(&reserved_oem);
+        }
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for RoutingActivationRequest {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
 impl Payload for RoutingActivationResponse {
     fn length(&self) -> usize {
         self.oem_specific
-            .map(|_| size::RARSP_OEM_SIZE)
-            .unwrap_or(size::RARSP_DEFAULT_SIZE)
+            .map(|_| RoutingActivationResponse::OEM_LENGTH)
+            .unwrap_or(RoutingActivationResponse::LENGTH)
     }
 
     fn payload_type() -> PayloadType {
@@ -87,11 +106,11 @@ impl Payload for RoutingActivationResponse {
         payload_length: usize,
     ) -> Result<(), DoIpError> {
         let has_oem_data: bool = match payload_length {
-            size::RARSP_DEFAULT_SIZE => Ok(false),
-            size::RARSP_OEM_SIZE => Ok(true),
+            RoutingActivationResponse::LENGTH => Ok(false),
+            RoutingActivationResponse::OEM_LENGTH => Ok(true),
             _ => Err(PayloadLengthTooShort {
                 value: payload_length as u32,
-                expected: size::RAREQ_DEFAULT_SIZE as u32,
+                expected: RoutingActivationRequest::LENGTH as u32,
             }),
         }?;
 
@@ -122,12 +141,29 @@ impl Payload for RoutingActivationResponse {
         }
         Ok(())
     }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        BigEndian::write_u16(&mut buf[0..2], self.logical_address_tester);
+        BigEndian::write_u16(&mut buf[2..4], self.logical_address_of_doip_entity);
+        buf[4] = self.routing_activation_response_code as u8;
+        buf[5..9].copy_from_slice(&self.reserved_oem);
+        if let Some(oem_specific) = self.oem_specific {
+            buf[9..13].copy_from_slice(&oem_specific);
+        }
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for RoutingActivationResponse {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
 }
 
-mod size {
+pub(crate) mod size {
     use crate::proto::routing_activation::*;
-    use crate::LogicalAddress;
-    use std::mem::size_of;
 
     pub const RAREQ_ZEROED: RoutingActivationRequest = RoutingActivationRequest {
         source_address: 0u16,
@@ -135,9 +171,6 @@ mod size {
         reserved: [0u8; 4],
         reserved_oem: Some([0u8; 4]),
     };
-    pub const RAREQ_DEFAULT_SIZE: usize =
-        size_of::<LogicalAddress>() + size_of::<u8>() + size_of::<[u8; 4]>();
-    pub const RAREQ_OEM_SIZE: usize = RAREQ_DEFAULT_SIZE + size_of::<[u8; 4]>();
 
     pub const RARSP_ZEROED: RoutingActivationResponse = RoutingActivationResponse {
         logical_address_tester: 0u16,
@@ -147,11 +180,6 @@ mod size {
         reserved_oem: [0; 4],
         oem_specific: Some([0; 4]),
     };
-    pub const RARSP_DEFAULT_SIZE: usize = size_of::<LogicalAddress>()
-        + size_of::<LogicalAddress>()
-        + size_of::<u8>()
-        + size_of::<[u8; 4]>();
-    pub const RARSP_OEM_SIZE: usize = RARSP_DEFAULT_SIZE + size_of::<[u8; 4]>();
 }
 
 impl TryFrom<u8> for ActivationType {
@@ -191,6 +219,7 @@ impl TryFrom<u8> for RoutingActivationResponseCode {
 mod test {
     use super::super::tests::*;
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn routing_activation_request() {
@@ -1,32 +1,33 @@
 #[cfg(test)]
 use crate::Payload;
-use crate::{read_message, write_message, DOIP_HEADER_LENGTH};
-use std::io::Cursor;
+use crate::{encode_message_into, read_message, write_message, DoIpError, DOIP_HEADER_LENGTH};
+use alloc::vec;
+use alloc::vec::Vec;
 
 fn assert_decode_smaller<P>(input: &[u8], _expected: &P)
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
     if !input.is_empty() {
-        let res = P::read(&mut Cursor::new(&input), input.len() - 1);
+        let res = P::read(&mut &input[..], input.len() - 1);
         assert!(res.is_err());
     }
 }
 
 fn assert_decode_bigger<P>(input: &[u8], _expected: &P)
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
     let mut bigger = Vec::new();
     bigger.extend_from_slice(input);
     bigger.push(0x12);
-    let res = P::read(&mut Cursor::new(&bigger), bigger.len());
+    let res = P::read(&mut &bigger[..], bigger.len());
     assert!(res.is_err());
 }
 
 fn assert_decode_corrupted<P>(input: &[u8], expected: &P, xor_corruptor: u8)
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
     assert_ne!(xor_corruptor, 0);
     if input.is_empty() {
@@ -35,7 +36,7 @@ where
     for i in 0..(input.len() - 1) {
         let mut corrupted = Vec::from(input);
         corrupted[i] ^= xor_corruptor;
-        let res = P::read(&mut Cursor::new(&corrupted), corrupted.len());
+        let res = P::read(&mut &corrupted[..], corrupted.len());
         if let Ok(p) = res {
             assert_ne!(&p, expected);
         }
@@ -44,9 +45,9 @@ where
 
 fn assert_decode_expected<P>(input: &[u8], expected: &P)
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
-    let res = P::read(&mut Cursor::new(&input), input.len());
+    let res = P::read(&mut &input[..], input.len());
     match res {
         Ok(msg) => assert_eq!(&msg, expected),
         Err(_) => panic!("Error in decoding input {:?}", input),
@@ -55,9 +56,9 @@ where
 
 fn assert_decode_with_header<P>(input: &[u8], expected: &P)
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
-    let res: Result<P, crate::DoIpError> = read_message(&mut Cursor::new(&input));
+    let res: Result<P, crate::DoIpError> = read_message(&mut &input[..]);
     match res {
         Ok(msg) => assert_eq!(&msg, expected),
         Err(_) => panic!("Error in decoding input {:?}", input),
@@ -66,7 +67,7 @@ where
 
 pub fn assert_decode_no_length_change<P>(expected: &P, input: &[u8])
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
     let payload = &input[DOIP_HEADER_LENGTH..];
 
@@ -78,7 +79,7 @@ where
 
 pub fn assert_decode<P>(expected: &P, input: &[u8])
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
     let payload = &input[DOIP_HEADER_LENGTH..];
 
@@ -90,9 +91,27 @@ where
 
 pub fn assert_encode<P>(input: &P, expected: &[u8])
 where
-    P: Payload + PartialEq + std::fmt::Debug,
+    P: Payload + PartialEq + core::fmt::Debug,
 {
-    let mut v: Vec<u8> = vec![];
-    write_message(input, &mut Cursor::new(&mut v)).unwrap();
+    let mut v: Vec<u8> = Vec::new();
+    write_message(input, &mut v).unwrap();
     assert_eq!(v, expected);
+
+    let mut buf = vec![0u8; expected.len() + 1];
+    assert_eq!(
+        encode_message_into(input, &mut buf).unwrap(),
+        expected.len()
+    );
+    assert_eq!(&buf[..expected.len()], expected);
+    let short = expected.len() - 1;
+    match encode_message_into(input, &mut buf[..short]) {
+        Err(DoIpError::BufferTooSmall { value, expected: e }) => {
+            assert_eq!((value, e), (short, expected.len()))
+        }
+        res => panic!("Unexpected result {:?}", res),
+    }
+
+    let mut payload = vec![0u8; input.length()];
+    assert_eq!(input.encode_into(&mut payload).unwrap(), input.length());
+    assert_eq!(payload, expected[DOIP_HEADER_LENGTH..]);
 }
@@ -0,0 +1,444 @@
+use crate::io::{Read, ReadBytesExt, Write, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder};
+
+use super::super::proto::vehicleident::*;
+use super::payload::payload_buffer;
+use crate::{BorrowedPayload, DoIpError, Payload, PayloadType};
+
+impl Payload for VehicleIdentificationRequest {
+    fn length(&self) -> usize {
+        0
+    }
+
+    fn payload_type() -> PayloadType {
+        PayloadType::VehicleIdentificationRequest
+    }
+
+    fn read<T: Read>(_reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        assert_empty_payload(payload_length)?;
+        Ok(VehicleIdentificationRequest {})
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        _reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        assert_empty_payload(payload_length)
+    }
+
+    fn write<T: Write>(&self, _writer: &mut T) -> Result<(), DoIpError> {
+        Ok(())
+    }
+
+    fn encode_into(&self, _buf: &mut [u8]) -> Result<usize, DoIpError> {
+        Ok(0)
+    }
+}
+
+impl BorrowedPayload<'_> for VehicleIdentificationRequest {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+impl Payload for VehicleIdentificationRequestWithEid {
+    fn payload_type() -> PayloadType {
+        PayloadType::VehicleIdentificationRequestWithEid
+    }
+
+    fn length(&self) -> usize {
+        VehicleIdentificationRequestWithEid::LENGTH
+    }
+
+    fn read<T: Read>(reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        let mut me = size::VIREQEID_ZEROED;
+        me.read_replace(reader, payload_length)?;
+        Ok(me)
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        use DoIpError::*;
+        if payload_length != VehicleIdentificationRequestWithEid::LENGTH {
+            return Err(PayloadLengthTooShort {
+                value: payload_length as u32,
+                expected: VehicleIdentificationRequestWithEid::LENGTH as u32,
+            });
+        }
+        reader.read_exact(&mut self.eid.0)?;
+        Ok(())
+    }
+
+    fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        writer.write_all(&self.eid.0)?;
+        Ok(())
+    }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, Self::LENGTH)?;
+        buf.copy_from_slice(&self.eid.0);
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for VehicleIdentificationRequestWithEid {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+impl Payload for VehicleIdentificationRequestWithVin {
+    fn payload_type() -> PayloadType {
+        PayloadType::VehicleIdentificationRequestWithVin
+    }
+
+    fn length(&self) -> usize {
+        VehicleIdentificationRequestWithVin::LENGTH
+    }
+
+    fn read<T: Read>(reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        let mut me = size::VIREQVIN_ZEROED;
+        me.read_replace(reader, payload_length)?;
+        Ok(me)
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        use DoIpError::*;
+        if payload_length != VehicleIdentificationRequestWithVin::LENGTH {
+            return Err(PayloadLengthTooShort {
+                value: payload_length as u32,
+                expected: VehicleIdentificationRequestWithVin::LENGTH as u32,
+            });
+        }
+        reader.read_exact(&mut self.vin.0)?;
+        Ok(())
+    }
+
+    fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        writer.write_all(&self.vin.0)?;
+        Ok(())
+    }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, Self::LENGTH)?;
+        buf.copy_from_slice(&self.vin.0);
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for VehicleIdentificationRequestWithVin {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+impl Payload for VehicleIdentificationResponse {
+    fn length(&self) -> usize {
+        self.vin_gid_sync_status
+            .map(|_| VehicleIdentificationResponse::SYNC_LENGTH)
+            .unwrap_or(VehicleIdentificationResponse::LENGTH)
+    }
+
+    fn payload_type() -> PayloadType {
+        PayloadType::VehicleIdentificationResponse
+    }
+
+    fn read<T: Read>(reader: &mut T, payload_length: usize) -> Result<Self, DoIpError> {
+        let mut me = size::VIR_ZEROED;
+        me.read_replace(reader, payload_length)?;
+        Ok(me)
+    }
+
+    fn read_replace<T: Read>(
+        &mut self,
+        reader: &mut T,
+        payload_length: usize,
+    ) -> Result<(), DoIpError> {
+        use DoIpError::*;
+        let has_sync_status = match payload_length {
+            VehicleIdentificationResponse::LENGTH => Ok(false),
+            VehicleIdentificationResponse::SYNC_LENGTH => Ok(true),
+            _ => Err(PayloadLengthTooShort {
+                value: payload_length as u32,
+                expected: VehicleIdentificationResponse::LENGTH as u32,
+            }),
+        }?;
+        reader.read_exact(&mut self.vin.0)?;
+        self.logical_address = reader.read_u16::<BigEndian>()?;
+        reader.read_exact(&mut self.eid.0)?;
+
+        let mut gid: Gid = [0x00; 6];
+        reader.read_exact(&mut gid)?;
+        // Table 1 - value not set
+        self.gid = if gid == [0x00; 6] || gid == [0xFF; 6] {
+            None
+        } else {
+            Some(gid)
+        };
+
+        let further_action_byte = reader.read_u8()?;
+        self.further_action = FurtherActionRequired::from(further_action_byte);
+
+        self.vin_gid_sync_status = match has_sync_status {
+            true => Some(VinGidSyncStatus::from(reader.read_u8()?)),
+            false => None,
+        };
+        Ok(())
+    }
+
+    fn write<T: Write>(&self, writer: &mut T) -> Result<(), DoIpError> {
+        let _ = writer.write(&self.vin.0)?;
+        writer.write_u16::<BigEndian>(self.logical_address)?;
+        let _ = writer.write(&self.eid.0)?;
+        let _ = writer.write(&self.gid.unwrap_or([0u8; 6]))?;
+        writer.write_u8(self.further_action.into())?;
+        if let Some(vin_gid_sync_status) = self.vin_gid_sync_status {
+            writer.write_u8(vin_gid_sync_status.into())?;
+        }
+        Ok(())
+    }
+
+    fn encode_into(&self, buf: &mut [u8]) -> Result<usize, DoIpError> {
+        let buf = payload_buffer(buf, self.length())?;
+        buf[0..17].copy_from_slice(&self.vin.0);
+        BigEndian::write_u16(&mut buf[17..19], self.logical_address);
+        buf[19..25].copy_from_slice(&self.eid.0);
+        buf[25..31].copy_from_slice(&self.gid.unwrap_or([0u8; 6]));
+        buf[31] = self.further_action.into();
+        if let Some(vin_gid_sync_status) = self.vin_gid_sync_status {
+            buf[32] = vin_gid_sync_status.into();
+        }
+        Ok(buf.len())
+    }
+}
+
+impl BorrowedPayload<'_> for VehicleIdentificationResponse {
+    fn read_borrowed(payload: &[u8]) -> Result<Self, DoIpError> {
+        let mut reader = payload;
+        Self::read(&mut reader, payload.len())
+    }
+}
+
+pub(crate) mod size {
+    use super::{Eid, VehicleIdentificationResponse};
+    use super::{FurtherActionRequired, VinGidSyncStatus};
+    use super::{VehicleIdentificationRequestWithEid, VehicleIdentificationRequestWithVin};
+    use crate::Vin;
+
+    pub const VIREQEID_ZEROED: VehicleIdentificationRequestWithEid =
+        VehicleIdentificationRequestWithEid { eid: Eid([0; 6]) };
+
+    pub const VIREQVIN_ZEROED: VehicleIdentificationRequestWithVin =
+        VehicleIdentificationRequestWithVin { vin: Vin([0; 17]) };
+
+    pub const VIR_ZEROED: VehicleIdentificationResponse = VehicleIdentificationResponse {
+        vin: Vin([0; 17]),
+        logical_address: 0,
+        eid: Eid([0; 6]),
+        gid: Some([0; 6]),
+        further_action: FurtherActionRequired::NoFurtherActionRequired,
+        vin_gid_sync_status: Some(VinGidSyncStatus::Synchronized),
+    };
+}
+
+impl From<u8> for VinGidSyncStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => VinGidSyncStatus::Synchronized,
+            0x10 => VinGidSyncStatus::Incomplete,
+            // 0x01..=0x0F and 0x11..=0xFF
+            _ => VinGidSyncStatus::Reserved(value),
+        }
+    }
+}
+
+impl From<VinGidSyncStatus> for u8 {
+    fn from(value: VinGidSyncStatus) -> Self {
+        use VinGidSyncStatus::*;
+        match value {
+            Synchronized => 0x00,
+            Incomplete => 0x10,
+            Reserved(value) => value,
+        }
+    }
+}
+
+impl From<u8> for FurtherActionRequired {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => FurtherActionRequired::NoFurtherActionRequired,
+            0x01..=0x0F => FurtherActionRequired::Reserved(value),
+            0x10 => FurtherActionRequired::RoutingActivationRequiredToInitiateCentralSecurity,
+            0x11..=0xFF => FurtherActionRequired::VmSpecific(value),
+        }
+    }
+}
+
+impl From<FurtherActionRequired> for u8 {
+    fn from(value: FurtherActionRequired) -> Self {
+        use FurtherActionRequired::*;
+        match value {
+            NoFurtherActionRequired => 0x00,
+            RoutingActivationRequiredToInitiateCentralSecurity => 0x10,
+            _ => 0xff,
+        }
+    }
+}
+
+fn assert_empty_payload(payload_length: usize) -> Result<(), DoIpError> {
+    use DoIpError::*;
+    if payload_length != 0 {
+        return Err(PayloadLengthTooShort {
+            value: payload_length as u32,
+            expected: 0u32,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::tests::*;
+    use super::*;
+    use crate::DOIP_HEADER_LENGTH;
+
+    #[test]
+    fn vehicle_identification_request() {
+        let payload = VehicleIdentificationRequest {};
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x01, // Payload type
+            0x00, 0x00, 0x00, 0x00, // Payload length
+        ];
+        assert_encode(&payload, &v);
+        assert_decode(&payload, &v);
+    }
+
+    #[test]
+    fn vehicle_identification_request_with_eid() {
+        let payload = VehicleIdentificationRequestWithEid {
+            eid: Eid([0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38]),
+        };
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x02, // Payload type
+            0x00, 0x00, 0x00, 0x06, // Payload length
+            0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38, // EID
+        ];
+        assert_encode(&payload, &v);
+        assert_decode(&payload, &v);
+    }
+
+    #[test]
+    fn vehicle_identification_request_with_vin() {
+        let payload = VehicleIdentificationRequestWithVin {
+            vin: Vin([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17]),
+        };
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x03, // Payload type
+            0x00, 0x00, 0x00, 0x11, // Payload length
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, // Vin
+        ];
+        assert_encode(&payload, &v);
+        assert_decode(&payload, &v);
+    }
+
+    #[test]
+    fn vehicle_identification_response() {
+        let vin = Vin([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17]);
+        let eid = Eid([0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38]);
+        let gid = None;
+        let payload = VehicleIdentificationResponse {
+            vin,
+            logical_address: 0xed00,
+            eid,
+            gid,
+            further_action: FurtherActionRequired::NoFurtherActionRequired,
+            vin_gid_sync_status: Some(VinGidSyncStatus::Synchronized),
+        };
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x04, // Payload type
+            0x00, 0x00, 0x00, 0x21, // Payload length
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, // Vin
+            0xed, 0x00, // LogicalAddress
+            0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38, // EID
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // GID
+            0x00, // FurtherActionRequired,
+            0x00, // VinGidSyncStatus
+        ];
+        assert_encode(&payload, &v);
+        // Both 32 and 33 bytes are valid payload lengths
+        assert_decode_no_length_change(&payload, &v);
+        assert_decode_vir_length(&v[DOIP_HEADER_LENGTH..]);
+    }
+
+    #[test]
+    fn vehicle_identification_response_without_sync_status() {
+        let payload = VehicleIdentificationResponse {
+            vin: Vin([0x30; 17]),
+            logical_address: 0x0077,
+            eid: Eid([0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38]),
+            gid: Some([0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x01]),
+            further_action:
+                FurtherActionRequired::RoutingActivationRequiredToInitiateCentralSecurity,
+            vin_gid_sync_status: None,
+        };
+        let v = [
+            0x02, 0xfd, // Protocol version
+            0x00, 0x04, // Payload type
+            0x00, 0x00, 0x00, 0x20, // Payload length
+            0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+            0x30, 0x30, 0x30, // Vin
+            0x00, 0x77, // LogicalAddress
+            0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x38, // EID
+            0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x01, // GID
+            0x10, // FurtherActionRequired,
+        ];
+        assert_encode(&payload, &v);
+        assert_decode_no_length_change(&payload, &v);
+        assert_decode_vir_length(&v[DOIP_HEADER_LENGTH..]);
+    }
+
+    fn assert_decode_vir_length(payload: &[u8]) {
+        let mut bigger = [0x00; VehicleIdentificationResponse::SYNC_LENGTH + 1];
+        bigger[..payload.len()].copy_from_slice(payload);
+        for length in [
+            VehicleIdentificationResponse::LENGTH - 1,
+            VehicleIdentificationResponse::SYNC_LENGTH + 1,
+        ] {
+            let res = VehicleIdentificationResponse::read(&mut &bigger[..], length);
+            assert!(res.is_err());
+        }
+        let res = VehicleIdentificationResponse::read(
+            &mut &bigger[..],
+            VehicleIdentificationResponse::LENGTH,
+        );
+        assert_eq!(res.unwrap().vin_gid_sync_status, None);
+    }
+
+    #[test]
+    fn vin_gid_sync_status() {
+        assert_eq!(VinGidSyncStatus::from(0x00), VinGidSyncStatus::Synchronized);
+        assert_eq!(VinGidSyncStatus::from(0x10), VinGidSyncStatus::Incomplete);
+        assert_eq!(
+            VinGidSyncStatus::from(0x42),
+            VinGidSyncStatus::Reserved(0x42)
+        );
+        for value in 0..=0xff {
+            assert_eq!(u8::from(VinGidSyncStatus::from(value)), value);
+        }
+    }
+}